@@ -0,0 +1,60 @@
+use std::convert::TryFrom;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tic_tac_toe::{BitBoard, Board, Piece, PieceGrid};
+
+// Scores the position for `turn` by searching every continuation, copying
+// the board for each move the way the AI does.
+fn negamax<G: PieceGrid>(board: &G, turn: Piece) -> i32 {
+    if board.winner(3).is_some() {
+        // Whoever just moved has won.
+        return -1;
+    }
+    board
+        .empty_positions()
+        .map(|(x, y)| {
+            -negamax(
+                &board.set_piece((x, y), turn).unwrap(),
+                turn.opponent().unwrap(),
+            )
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+// The same search, playing and taking back moves on one board.
+fn negamax_in_place(board: &mut BitBoard, turn: Piece) -> i32 {
+    if board.line_winner().is_some() {
+        return -1;
+    }
+    let moves = board.empty_positions().collect::<Vec<_>>();
+    let mut best = None;
+    for (x, y) in moves {
+        board.make((x, y), turn).unwrap();
+        let score = -negamax_in_place(board, turn.opponent().unwrap());
+        board.unmake((x, y)).unwrap();
+        best = best.max(Some(score));
+    }
+    best.unwrap_or(0)
+}
+
+fn vec_vs_bits(c: &mut Criterion) {
+    // An opening move in the corner, leaving eight moves to search.
+    let board = Board::new().set_piece((0, 0), Piece::Naught).unwrap();
+    let bits = BitBoard::try_from(&board).unwrap();
+
+    let mut group = c.benchmark_group("minimax_3x3");
+    group.bench_function("vec", |b| {
+        b.iter(|| negamax(black_box(&board), Piece::Cross))
+    });
+    group.bench_function("bitboard", |b| {
+        b.iter(|| negamax(black_box(&bits), Piece::Cross))
+    });
+    group.bench_function("bitboard_make_unmake", |b| {
+        b.iter(|| negamax_in_place(&mut black_box(bits), Piece::Cross))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, vec_vs_bits);
+criterion_main!(benches);
@@ -0,0 +1,20 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use tic_tac_toe::{Board, Piece};
+
+fn set_piece(c: &mut Criterion) {
+    let mut group = c.benchmark_group("set_piece");
+    for width in [3, 10, 50] {
+        let board = Board::with_size(width);
+        group.bench_with_input(BenchmarkId::from_parameter(width), &board, |b, board| {
+            b.iter(|| {
+                black_box(board)
+                    .set_piece((width / 2, width / 2), Piece::Cross)
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, set_piece);
+criterion_main!(benches);
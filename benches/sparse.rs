@@ -0,0 +1,41 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tic_tac_toe::{Board, Piece, PieceGrid, SparseBoard};
+
+const WIDTH: usize = 50;
+const WIN_LENGTH: usize = 5;
+
+// A 50x50 connect-5 board with a handful of pieces down the diagonal.
+fn mostly_empty<G: PieceGrid>(board: G) -> G {
+    (0..10).fold(board, |board, i| {
+        let piece = if i % 2 == 0 {
+            Piece::Naught
+        } else {
+            Piece::Cross
+        };
+        board.set_piece((i * 4, i * 4), piece).unwrap()
+    })
+}
+
+fn dense_vs_sparse(c: &mut Criterion) {
+    let dense = mostly_empty(Board::with_size(WIDTH));
+    let sparse = mostly_empty(SparseBoard::with_size(WIDTH));
+
+    let mut group = c.benchmark_group("set_piece_50x50");
+    group.bench_function("dense", |b| {
+        b.iter(|| black_box(&dense).set_piece((1, 0), Piece::Cross).unwrap())
+    });
+    group.bench_function("sparse", |b| {
+        b.iter(|| black_box(&sparse).set_piece((1, 0), Piece::Cross).unwrap())
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("winner_50x50");
+    group.bench_function("dense", |b| b.iter(|| black_box(&dense).winner(WIN_LENGTH)));
+    group.bench_function("sparse", |b| {
+        b.iter(|| black_box(&sparse).winner(WIN_LENGTH))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, dense_vs_sparse);
+criterion_main!(benches);
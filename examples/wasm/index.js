@@ -0,0 +1,36 @@
+// Build the package first with:
+//   wasm-pack build --target web --out-dir examples/wasm/pkg -- --features wasm
+// then serve this directory with any static file server.
+import init, { Game } from "./pkg/tic_tac_toe.js";
+
+await init();
+
+const game = new Game(3);
+const board = document.getElementById("board");
+const status = document.getElementById("status");
+
+function render(message) {
+  board.textContent = game.boardString();
+  const state = game.status();
+  if (state === "in-progress") {
+    status.textContent = message ?? `${game.currentTurn()} to play`;
+  } else if (state === "draw") {
+    status.textContent = "It's a draw!";
+  } else {
+    status.textContent = `${state} won!`;
+  }
+}
+
+document.getElementById("move").addEventListener("submit", (event) => {
+  event.preventDefault();
+  const x = Number(document.getElementById("x").value);
+  const y = Number(document.getElementById("y").value);
+  try {
+    game.applyMove(x, y);
+    render();
+  } catch (error) {
+    render(`Invalid move: ${error}`);
+  }
+});
+
+render();
@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tic_tac_toe::Board;
+
+// Parsing must never panic, and any board that parses must survive a round
+// trip through its Display output.
+fuzz_target!(|input: &str| {
+    if let Ok(board) = input.parse::<Board>() {
+        let reparsed = board
+            .to_string()
+            .parse::<Board>()
+            .expect("a displayed board parses");
+        assert_eq!(board, reparsed);
+    }
+});
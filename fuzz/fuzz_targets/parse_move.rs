@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tic_tac_toe::{parse_cell_number, parse_move};
+
+// Any string, including unicode, embedded nulls and huge inputs, must come
+// back as a move or a TicTacToeError rather than a panic.
+fuzz_target!(|input: &str| {
+    let _ = parse_move(input);
+    for (width, height) in [(1, 1), (3, 3), (50, 50), (4, 3), (3, 7)] {
+        if let Ok((x, y)) = parse_cell_number(input, width, height) {
+            assert!(x < width && y < height);
+        }
+    }
+});
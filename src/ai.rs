@@ -0,0 +1,372 @@
+use crate::board::{Board, WIN_SCORE};
+use crate::piece::Piece;
+use crate::position::Position;
+
+/// Something that can pick a move for `turn`, such as an AI under test.
+pub trait Strategy {
+    fn choose(&self, board: &Board, turn: Piece) -> (usize, usize);
+}
+
+// About how many positions a search may reach before it stops short of the
+// end of the game and estimates the rest, so big boards stay responsive.
+const SEARCH_BUDGET: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Perfect,
+    /// Wins or blocks when it can, and otherwise takes the centre, then a
+    /// corner, then an edge. Always picks the same move for the same board.
+    Easy,
+    #[cfg(feature = "rand")]
+    Random,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AiPlayer {
+    piece: Piece,
+    difficulty: Difficulty,
+    reverse: bool,
+}
+
+impl AiPlayer {
+    pub fn new(piece: Piece, difficulty: Difficulty) -> Self {
+        AiPlayer {
+            piece,
+            difficulty,
+            reverse: false,
+        }
+    }
+
+    /// Plays the misère variant, where completing a line loses, so the AI
+    /// steers clear of lines instead of going for them.
+    pub fn with_reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    pub fn piece(&self) -> Piece {
+        self.piece
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    pub fn best_move(&self, board: &Board, winning_positions: &[Vec<Position>]) -> (usize, usize) {
+        match self.difficulty {
+            Difficulty::Perfect => self.minimax_move(board, winning_positions),
+            Difficulty::Easy => self.priority_move(board, winning_positions),
+            #[cfg(feature = "rand")]
+            Difficulty::Random => {
+                use rand::seq::IteratorRandom;
+                board
+                    .empty_positions()
+                    .choose(&mut rand::thread_rng())
+                    .expect("the AI was asked to move on a full board")
+            }
+        }
+    }
+
+    /// Scores every empty cell by the minimax value of playing there, where
+    /// higher is better for this player. Scores near 1,000,000 are forced
+    /// wins and near -1,000,000 forced losses, with quicker wins scoring
+    /// higher. On boards too big to search to the end, smaller scores are
+    /// `Board::evaluate`'s estimate of the position the search stopped at.
+    pub fn score_moves(
+        &self,
+        board: &Board,
+        winning_positions: &[Vec<Position>],
+    ) -> Vec<((usize, usize), i32)> {
+        let search = Search::new(board, winning_positions, self.reverse);
+        board
+            .empty_positions()
+            .map(|(x, y)| {
+                let next = board
+                    .set_piece((x, y), self.piece)
+                    .expect("legal moves are always in bounds and empty");
+                let score = -search.negamax(&next, self.piece, 1, i32::MIN + 1, i32::MAX);
+                ((x, y), score)
+            })
+            .collect()
+    }
+
+    fn minimax_move(&self, board: &Board, winning_positions: &[Vec<Position>]) -> (usize, usize) {
+        let search = Search::new(board, winning_positions, self.reverse);
+        let mut best = None;
+        let mut alpha = i32::MIN + 1;
+        for (x, y) in board.empty_positions() {
+            let next = board
+                .set_piece((x, y), self.piece)
+                .expect("legal moves are always in bounds and empty");
+            let score = -search.negamax(&next, self.piece, 1, i32::MIN + 1, -alpha);
+            if best.is_none() || score > alpha {
+                best = Some((x, y));
+                alpha = score;
+            }
+        }
+        best.expect("the AI was asked to move on a full board")
+    }
+
+    fn priority_move(&self, board: &Board, winning_positions: &[Vec<Position>]) -> (usize, usize) {
+        let win_length = winning_positions.first().map_or(board.width(), Vec::len);
+        // Completing a line loses the misère game, so those moves are the
+        // ones to avoid, and there is nothing to block.
+        let losing = if self.reverse {
+            board.winning_moves(self.piece, win_length)
+        } else {
+            Vec::new()
+        };
+        let urgent = if self.reverse {
+            None
+        } else {
+            board
+                .winning_moves(self.piece, win_length)
+                .into_iter()
+                .chain(board.blocking_moves(self.piece, win_length))
+                .next()
+        };
+        let safe = board
+            .empty_positions()
+            .filter(|position| !losing.contains(position))
+            .collect::<Vec<_>>();
+        let candidates = if safe.is_empty() {
+            board.empty_positions().collect()
+        } else {
+            safe
+        };
+        urgent
+            .or_else(|| {
+                candidates.into_iter().min_by_key(|&(x, y)| {
+                    if board.is_center(x, y) {
+                        0
+                    } else if board.is_corner(x, y) {
+                        1
+                    } else if board.is_edge(x, y) {
+                        2
+                    } else {
+                        3
+                    }
+                })
+            })
+            .expect("the AI was asked to move on a full board")
+    }
+}
+
+// A minimax search from one position, shared by every move tried there.
+struct Search<'a> {
+    winning_positions: &'a [Vec<Position>],
+    win_length: usize,
+    // Whether completing a line loses, as in the misère variant.
+    reverse: bool,
+    // How many pieces past the starting position the search places before
+    // it estimates the position with `Board::evaluate` instead.
+    limit: i32,
+}
+
+impl<'a> Search<'a> {
+    // Searches as deep as the budget allows. With at most nine empty cells,
+    // as on any 3x3 board, every game is played out, so small boards are
+    // still played perfectly.
+    fn new(board: &Board, winning_positions: &'a [Vec<Position>], reverse: bool) -> Self {
+        let empty = board.empty_positions().count();
+        let mut limit = 0;
+        let mut positions = 1;
+        while limit < empty && positions * (empty - limit) <= SEARCH_BUDGET {
+            positions *= empty - limit;
+            limit += 1;
+        }
+        Search {
+            winning_positions,
+            win_length: winning_positions.first().map_or(board.width(), Vec::len),
+            reverse,
+            limit: if empty <= 9 {
+                i32::MAX
+            } else {
+                limit.max(1) as i32
+            },
+        }
+    }
+
+    // Scores `board` from the point of view of the player about to move,
+    // where `last` is the piece that has just been placed `depth` moves in.
+    // In the misère variant a completed line loses for whoever made it, so
+    // the scores flip.
+    fn negamax(&self, board: &Board, last: Piece, depth: i32, mut alpha: i32, beta: i32) -> i32 {
+        if has_line(board, self.winning_positions, last) {
+            return if self.reverse {
+                WIN_SCORE - depth
+            } else {
+                depth - WIN_SCORE
+            };
+        }
+        let moves = board.empty_positions().collect::<Vec<_>>();
+        if moves.is_empty() {
+            return 0;
+        }
+        // The search only models two-player games, and a Triangle is
+        // searched as if it were playing Naught.
+        let turn = last.opponent().unwrap_or(Piece::Naught);
+        if depth >= self.limit {
+            let estimate = board.evaluate(turn, self.win_length);
+            return if self.reverse { -estimate } else { estimate };
+        }
+        let mut best = i32::MIN + 1;
+        for (x, y) in moves {
+            let next = board
+                .set_piece((x, y), turn)
+                .expect("legal moves are always in bounds and empty");
+            let score = -self.negamax(&next, turn, depth + 1, -beta, -alpha);
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+}
+
+fn has_line(board: &Board, winning_positions: &[Vec<Position>], piece: Piece) -> bool {
+    winning_positions.iter().any(|slice| {
+        slice
+            .iter()
+            .all(|&position| matches!(board.get_position(position), Ok(Some(p)) if p == piece))
+    })
+}
+
+impl Strategy for AiPlayer {
+    /// Plays `turn` at this player's difficulty, assuming lines must span the
+    /// board's shorter side.
+    fn choose(&self, board: &Board, turn: Piece) -> (usize, usize) {
+        let winning_positions = board.lines_of(board.width().min(board.height()));
+        AiPlayer {
+            piece: turn,
+            ..*self
+        }
+        .best_move(board, &winning_positions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::board::winning_positions;
+
+    // Generous enough for a debug build on a slow machine, while a search
+    // played out to the end takes minutes.
+    const MOVE_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+    // Plays every reply `opponent` could make against the AI from `board`,
+    // with `turn` to move, and checks the AI never loses.
+    fn never_loses(ai: &AiPlayer, board: &Board, turn: Piece, lines: &[Vec<Position>]) {
+        let opponent = ai.piece().opponent().unwrap();
+        assert!(!has_line(board, lines, opponent), "lost on\n{}", board);
+        if has_line(board, lines, ai.piece()) || board.is_full() {
+            return;
+        }
+        if turn == ai.piece() {
+            let next = board.set_piece(ai.best_move(board, lines), turn).unwrap();
+            never_loses(ai, &next, opponent, lines);
+        } else {
+            for position in board.empty_positions() {
+                let next = board.set_piece(position, turn).unwrap();
+                never_loses(ai, &next, ai.piece(), lines);
+            }
+        }
+    }
+
+    #[test]
+    fn perfect_play_never_loses_on_3x3() {
+        let lines = winning_positions(3, 3, 3);
+        let ai = AiPlayer::new(Piece::Naught, Difficulty::Perfect);
+        never_loses(&ai, &Board::new(), Piece::Naught, &lines);
+        never_loses(&ai, &Board::new(), Piece::Cross, &lines);
+    }
+
+    #[test]
+    fn opening_move_on_3x3_is_optimal() {
+        let lines = winning_positions(3, 3, 3);
+        let ai = AiPlayer::new(Piece::Naught, Difficulty::Perfect);
+        let board = Board::new();
+        let scores = ai.score_moves(&board, &lines);
+        // Every opening draws with best play, so nothing should score as a
+        // forced win or loss.
+        assert_eq!(scores.len(), 9);
+        assert!(scores.iter().all(|&(_, score)| score == 0));
+    }
+
+    #[test]
+    fn blocks_a_two_in_a_row_on_3x3() {
+        let board = Board::new()
+            .apply_moves(&[
+                (0, 0, Piece::Cross),
+                (1, 1, Piece::Naught),
+                (1, 0, Piece::Cross),
+            ])
+            .unwrap();
+        let lines = winning_positions(3, 3, 3);
+        let ai = AiPlayer::new(Piece::Naught, Difficulty::Perfect);
+        assert_eq!(ai.best_move(&board, &lines), (2, 0));
+    }
+
+    #[test]
+    fn perfect_moves_on_big_boards_are_quick() {
+        for &(size, win_length) in &[(4, 4), (5, 5), (5, 3)] {
+            let board = Board::with_size(size);
+            let lines = winning_positions(size, size, win_length);
+            let started = Instant::now();
+            AiPlayer::new(Piece::Naught, Difficulty::Perfect).best_move(&board, &lines);
+            assert!(
+                started.elapsed() < MOVE_TIME_LIMIT,
+                "a {}x{} move took {:?}",
+                size,
+                size,
+                started.elapsed()
+            );
+        }
+    }
+
+    #[test]
+    fn depth_limited_search_still_blocks() {
+        // Cross threatens the top row of an otherwise open 4x4 board.
+        let board = Board::with_size(4)
+            .apply_moves(&[
+                (0, 0, Piece::Cross),
+                (3, 3, Piece::Naught),
+                (1, 0, Piece::Cross),
+                (3, 2, Piece::Naught),
+                (2, 0, Piece::Cross),
+            ])
+            .unwrap();
+        let lines = winning_positions(4, 4, 4);
+        let ai = AiPlayer::new(Piece::Naught, Difficulty::Perfect);
+        assert_eq!(ai.best_move(&board, &lines), (3, 0));
+    }
+
+    #[test]
+    fn reverse_play_avoids_completing_a_line() {
+        // Naught would complete the left column by playing 0 2.
+        let board = Board::new()
+            .apply_moves(&[
+                (0, 0, Piece::Naught),
+                (2, 2, Piece::Cross),
+                (0, 1, Piece::Naught),
+                (1, 2, Piece::Cross),
+            ])
+            .unwrap();
+        let lines = winning_positions(3, 3, 3);
+        for &difficulty in &[Difficulty::Perfect, Difficulty::Easy] {
+            let ai = AiPlayer::new(Piece::Naught, difficulty);
+            assert_eq!(ai.best_move(&board, &lines), (0, 2));
+            let ai = ai.with_reverse(true);
+            assert_ne!(ai.best_move(&board, &lines), (0, 2));
+        }
+        let scores = AiPlayer::new(Piece::Naught, Difficulty::Perfect)
+            .with_reverse(true)
+            .score_moves(&board, &lines);
+        let worst = scores.iter().min_by_key(|&&(_, score)| score).unwrap();
+        assert_eq!(*worst, ((0, 2), 1 - WIN_SCORE));
+    }
+}
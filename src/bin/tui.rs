@@ -0,0 +1,238 @@
+//! A full-screen frontend: move the cursor with the arrow keys and press
+//! Enter to place a piece. Run it with `cargo run --features tui --bin tui`,
+//! optionally followed by a board size and `--vs-ai`.
+
+use std::env;
+use std::io::{self, Stdout, Write};
+use std::process;
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use tic_tac_toe::{AiPlayer, Difficulty, Game, GameStatus, Piece, Player, Position};
+
+// Each cell is drawn three characters wide with a one character border.
+const CELL_WIDTH: u16 = 4;
+const HELP: &str = "Arrows move, Enter places, u undoes, n starts a new game, q quits";
+
+// Puts the terminal into raw mode on the alternate screen, and puts it back
+// when dropped, so the shell is restored however the program ends, panics
+// included.
+struct Screen {
+    stdout: Stdout,
+}
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, Hide)?;
+        Ok(Screen { stdout })
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        execute!(self.stdout, Show, LeaveAlternateScreen).ok();
+        terminal::disable_raw_mode().ok();
+    }
+}
+
+struct Tui {
+    game: Game,
+    cursor: (usize, usize),
+    message: String,
+}
+
+impl Tui {
+    fn new(game: Game) -> Self {
+        let (width, height) = game.board().dimensions();
+        Tui {
+            game,
+            cursor: (width / 2, height / 2),
+            message: String::new(),
+        }
+    }
+
+    // Handles a key press, returning false once the player asks to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let (width, height) = self.game.board().dimensions();
+        let (x, y) = self.cursor;
+        self.message.clear();
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Left | KeyCode::Char('h') => self.cursor = (x.saturating_sub(1), y),
+            KeyCode::Right | KeyCode::Char('l') => self.cursor = ((x + 1).min(width - 1), y),
+            KeyCode::Up | KeyCode::Char('k') => self.cursor = (x, y.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => self.cursor = (x, (y + 1).min(height - 1)),
+            KeyCode::Enter | KeyCode::Char(' ') => match self.game.apply_move(x, y) {
+                Ok(()) => self.play_ai(),
+                Err(e) => self.message = format!("Can't play there: {}", e),
+            },
+            KeyCode::Char('u') => match self.game.undo() {
+                // Take back the computer's reply too, as `run` does.
+                Ok(()) => {
+                    if self.is_ai_turn() {
+                        self.game.undo().ok();
+                    }
+                }
+                Err(e) => self.message = format!("Can't undo: {}", e),
+            },
+            KeyCode::Char('n') => {
+                self.game.start_new_game();
+                self.play_ai();
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn is_ai_turn(&self) -> bool {
+        matches!(self.game.player(self.game.current_turn()), Player::Ai(_))
+    }
+
+    // Lets the computer move for as long as it is its turn.
+    fn play_ai(&mut self) {
+        while self.game.status() == GameStatus::InProgress {
+            let ai = match self.game.player(self.game.current_turn()) {
+                Player::Ai(ai) => ai,
+                Player::Human => break,
+            };
+            let (x, y) = ai.best_move(self.game.board(), self.game.winning_positions());
+            self.game
+                .apply_move(x, y)
+                .expect("the AI picked an illegal move");
+        }
+    }
+
+    fn status_line(&self) -> String {
+        match self.game.status() {
+            GameStatus::Win(winner) => format!("{} won! Press n to play again.", winner),
+            GameStatus::Draw => "It's a draw! Press n to play again.".to_string(),
+            GameStatus::InProgress => format!("{} to move.", self.game.current_turn()),
+        }
+    }
+
+    // Redraws everything, centred in the terminal. The whole screen is
+    // cleared each time so that nothing is left behind after a resize.
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let (columns, rows) = terminal::size()?;
+        let (width, height) = self.game.board().dimensions();
+        let board_columns = width as u16 * CELL_WIDTH + 1;
+        let board_rows = height as u16 * 2 + 1;
+        queue!(out, Clear(ClearType::All))?;
+        if columns < board_columns.max(HELP.len() as u16) || rows < board_rows + 4 {
+            queue!(
+                out,
+                MoveTo(0, 0),
+                Print("Make the terminal bigger to see the board.")
+            )?;
+            return out.flush();
+        }
+        let left = (columns - board_columns) / 2;
+        let top = (rows - board_rows - 4) / 2;
+        let winning_line = self
+            .game
+            .winning_line()
+            .map_or_else(Vec::new, |(_, line)| line);
+        let border = format!("+{}", "---+".repeat(width));
+        for y in 0..height {
+            let row = top + y as u16 * 2;
+            queue!(
+                out,
+                MoveTo(left, row),
+                Print(&border),
+                MoveTo(left, row + 1),
+                Print("|")
+            )?;
+            for x in 0..width {
+                let cell = self.game.board()[(x, y)].map_or(" ".to_string(), |p| p.to_string());
+                if winning_line.contains(&Position::new(x, y)) {
+                    queue!(
+                        out,
+                        SetForegroundColor(Color::Green),
+                        SetAttribute(Attribute::Bold)
+                    )?;
+                }
+                if (x, y) == self.cursor {
+                    queue!(out, SetAttribute(Attribute::Reverse))?;
+                }
+                queue!(
+                    out,
+                    Print(format!(" {} ", cell)),
+                    SetAttribute(Attribute::Reset),
+                    Print("|")
+                )?;
+            }
+        }
+        let bottom = top + board_rows;
+        let centred = |text: &str| (columns.saturating_sub(text.len() as u16)) / 2;
+        let status = self.status_line();
+        queue!(
+            out,
+            MoveTo(left, bottom - 1),
+            Print(&border),
+            MoveTo(centred(&status), bottom + 1),
+            Print(&status),
+            MoveTo(centred(&self.message), bottom + 2),
+            Print(&self.message),
+            MoveTo(centred(HELP), bottom + 3),
+            Print(HELP)
+        )?;
+        out.flush()
+    }
+}
+
+fn parse_args() -> Result<Game, String> {
+    let mut size = None;
+    let mut vs_ai = false;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--vs-ai" => vs_ai = true,
+            _ => match arg.parse::<usize>() {
+                Ok(width) if width > 0 => size = Some(width),
+                _ => return Err(format!("usage: tui [SIZE] [--vs-ai], not {}", arg)),
+            },
+        }
+    }
+    let game = Game::new(size);
+    Ok(if vs_ai {
+        game.with_ai(AiPlayer::new(Piece::Cross, Difficulty::Perfect))
+    } else {
+        game
+    })
+}
+
+fn run(game: Game) -> io::Result<()> {
+    let mut screen = Screen::enter()?;
+    let mut tui = Tui::new(game);
+    tui.draw(&mut screen.stdout)?;
+    loop {
+        match event::read()? {
+            // Windows reports key releases too, which shouldn't move twice.
+            Event::Key(key) if key.kind != KeyEventKind::Release => {
+                if !tui.handle_key(key) {
+                    return Ok(());
+                }
+            }
+            Event::Resize(..) => {}
+            _ => continue,
+        }
+        tui.draw(&mut screen.stdout)?;
+    }
+}
+
+fn main() {
+    let game = parse_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    if let Err(e) = run(game) {
+        eprintln!("The terminal stopped working: {}", e);
+        process::exit(1);
+    }
+}
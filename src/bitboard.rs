@@ -0,0 +1,166 @@
+use core::convert::TryFrom;
+
+use crate::board::{lines, Board};
+use crate::error::TicTacToeError;
+use crate::grid::PieceGrid;
+use crate::piece::{BoardPosition, Piece};
+use crate::position::Position;
+
+const WIDTH: usize = 3;
+
+// Bit `y * 3 + x` is set for every cell of each row, column and diagonal.
+const LINES: [u16; 8] = [
+    0b000_000_111,
+    0b000_111_000,
+    0b111_000_000,
+    0b001_001_001,
+    0b010_010_010,
+    0b100_100_100,
+    0b100_010_001,
+    0b001_010_100,
+];
+
+/// A 3x3 board packed into one bit mask per piece, for searches that need to
+/// place and check thousands of positions quickly. Checking for a winner is
+/// a handful of mask comparisons, and `make`/`unmake` play and take back a
+/// move without copying. Only naughts and crosses fit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BitBoard {
+    naughts: u16,
+    crosses: u16,
+}
+
+impl BitBoard {
+    pub fn new() -> Self {
+        BitBoard::default()
+    }
+
+    fn bit(position: impl Into<Position>) -> Result<u16, TicTacToeError> {
+        let Position { x, y } = position.into();
+        if x >= WIDTH || y >= WIDTH {
+            return Err(TicTacToeError::OutOfBounds(x, y));
+        }
+        Ok(1 << (y * WIDTH + x))
+    }
+
+    pub fn get(&self, position: impl Into<Position>) -> Result<BoardPosition, TicTacToeError> {
+        let bit = BitBoard::bit(position)?;
+        Ok(if self.naughts & bit != 0 {
+            Some(Piece::Naught)
+        } else if self.crosses & bit != 0 {
+            Some(Piece::Cross)
+        } else {
+            None
+        })
+    }
+
+    /// Plays `piece` at `position` in place.
+    pub fn make(
+        &mut self,
+        position: impl Into<Position>,
+        piece: Piece,
+    ) -> Result<(), TicTacToeError> {
+        let position = position.into();
+        let bit = BitBoard::bit(position)?;
+        if (self.naughts | self.crosses) & bit != 0 {
+            return Err(TicTacToeError::PieceInPosition(position.x, position.y));
+        }
+        match piece {
+            Piece::Naught => self.naughts |= bit,
+            Piece::Cross => self.crosses |= bit,
+            Piece::Triangle => {
+                return Err(TicTacToeError::InvalidBoard(
+                    "a BitBoard only holds naughts and crosses".to_string(),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes back whatever was played at `position`.
+    pub fn unmake(&mut self, position: impl Into<Position>) -> Result<(), TicTacToeError> {
+        let bit = BitBoard::bit(position)?;
+        self.naughts &= !bit;
+        self.crosses &= !bit;
+        Ok(())
+    }
+
+    pub fn is_full(&self) -> bool {
+        (self.naughts | self.crosses) == 0b111_111_111
+    }
+
+    /// The piece holding a whole row, column or diagonal, if any.
+    pub fn line_winner(&self) -> Option<Piece> {
+        let holds = |mask: u16| LINES.iter().any(|&line| line & !mask == 0);
+        if holds(self.naughts) {
+            Some(Piece::Naught)
+        } else if holds(self.crosses) {
+            Some(Piece::Cross)
+        } else {
+            None
+        }
+    }
+}
+
+impl PieceGrid for BitBoard {
+    fn width(&self) -> usize {
+        WIDTH
+    }
+
+    fn get_position(&self, position: impl Into<Position>) -> Result<BoardPosition, TicTacToeError> {
+        self.get(position)
+    }
+
+    fn set_piece(
+        &self,
+        position: impl Into<Position>,
+        piece: Piece,
+    ) -> Result<Self, TicTacToeError> {
+        let mut board = *self;
+        board.make(position, piece)?;
+        Ok(board)
+    }
+
+    fn empty_positions(&self) -> Box<dyn Iterator<Item = (usize, usize)> + '_> {
+        let occupied = self.naughts | self.crosses;
+        Box::new(
+            (0..WIDTH * WIDTH)
+                .filter(move |i| occupied & (1 << i) == 0)
+                .map(|i| (i % WIDTH, i / WIDTH)),
+        )
+    }
+
+    // Shorter lines don't have precomputed masks, so only full lines are
+    // checked quickly.
+    fn winner(&self, win_length: usize) -> Option<Piece> {
+        match win_length {
+            WIDTH => self.line_winner(),
+            _ => lines(WIDTH, WIDTH, win_length).find_map(|line| {
+                let piece = self.get(line[0]).ok()??;
+                line.iter()
+                    .all(|&position| matches!(self.get(position), Ok(Some(p)) if p == piece))
+                    .then_some(piece)
+            }),
+        }
+    }
+}
+
+impl TryFrom<&Board> for BitBoard {
+    type Error = TicTacToeError;
+
+    /// Packs a 3x3 board of naughts and crosses.
+    fn try_from(board: &Board) -> Result<Self, Self::Error> {
+        if (board.width(), board.height()) != (WIDTH, WIDTH) {
+            return Err(TicTacToeError::InvalidBoard(format!(
+                "a BitBoard is 3x3, not {}x{}",
+                board.width(),
+                board.height()
+            )));
+        }
+        let mut packed = BitBoard::new();
+        for (x, y, piece) in board.occupied_positions() {
+            packed.make((x, y), piece)?;
+        }
+        Ok(packed)
+    }
+}
@@ -0,0 +1,1448 @@
+use core::fmt::{self, Display};
+use core::ops::Index;
+use core::str::FromStr;
+use std::collections::HashMap;
+#[cfg(feature = "color")]
+use std::io;
+use std::sync::OnceLock;
+
+use crate::error::TicTacToeError;
+use crate::piece::{BoardPosition, Piece};
+use crate::position::Position;
+use crate::symbols::PieceSymbols;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "color")]
+use termcolor::{Color, ColorSpec, WriteColor};
+
+const DEFAULT_BOARD_WIDTH: usize = 3;
+pub(crate) const WIN_SCORE: i32 = 1_000_000;
+// Cells with a precomputed Zobrist key, enough for a 16x16 board. Bigger
+// boards work out the keys for the remaining cells as they need them.
+const ZOBRIST_CELLS: usize = 256;
+
+static ZOBRIST_KEYS: OnceLock<Vec<u64>> = OnceLock::new();
+
+/// A grid of cells stored row by row, usually square. The game plays on a
+/// `TicTacToeBoard`, while other cell types can reuse the indexing and
+/// symmetry logic.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Board<T = BoardPosition> {
+    width: usize,
+    // Saves from before boards could be rectangular have no height, which
+    // reads back as zero and is fixed up to the width on load.
+    #[cfg_attr(feature = "serde", serde(default))]
+    height: usize,
+    positions: Vec<T>,
+}
+
+pub type TicTacToeBoard = Board<BoardPosition>;
+
+/// A line one piece short of a win, and the empty cell that would complete
+/// it.
+pub type Threat = (Vec<(usize, usize)>, (usize, usize));
+
+impl<T> Board<T> {
+    pub fn filled(width: usize, value: T) -> Self
+    where
+        T: Clone,
+    {
+        Board::filled_rect(width, width, value)
+    }
+
+    /// A board `width` cells across and `height` rows down, every cell set
+    /// to `value`.
+    pub fn filled_rect(width: usize, height: usize, value: T) -> Self
+    where
+        T: Clone,
+    {
+        Board {
+            width,
+            height,
+            positions: vec![value; width * height],
+        }
+    }
+
+    /// Builds a board directly from its row-major positions, whose length
+    /// must be a non-zero perfect square.
+    pub fn from_positions(positions: Vec<T>) -> Result<Self, TicTacToeError> {
+        let width = (1..=positions.len())
+            .find(|width| width * width >= positions.len())
+            .filter(|width| width * width == positions.len())
+            .ok_or_else(|| {
+                TicTacToeError::InvalidBoard(format!(
+                    "{} positions do not make a square board",
+                    positions.len()
+                ))
+            })?;
+        Ok(Board {
+            width,
+            height: width,
+            positions,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The board's (width, height).
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.width == self.height
+    }
+
+    /// The number of cells on the board, whether or not they're taken.
+    pub fn cell_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn get_position(&self, position: impl Into<Position>) -> Result<T, TicTacToeError>
+    where
+        T: Clone,
+    {
+        Ok(self.positions[self.index(position.into())?].clone())
+    }
+
+    /// Replaces whatever is at `position`, unlike `set_piece` which refuses
+    /// to overwrite a piece.
+    pub fn set_position(
+        &self,
+        position: impl Into<Position>,
+        value: T,
+    ) -> Result<Self, TicTacToeError>
+    where
+        T: Clone,
+    {
+        let mut board = self.clone();
+        board.set_position_mut(position, value)?;
+        Ok(board)
+    }
+
+    pub fn set_position_mut(
+        &mut self,
+        position: impl Into<Position>,
+        value: T,
+    ) -> Result<(), TicTacToeError> {
+        let index = self.index(position.into())?;
+        self.positions[index] = value;
+        Ok(())
+    }
+
+    /// Every cell with its coordinates, in row-major order.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize, &T)> + '_ {
+        self.positions
+            .iter()
+            .enumerate()
+            .map(move |(i, value)| (i % self.width, i / self.width, value))
+    }
+
+    /// The cells that differ between this board and `other`, as (x, y, old,
+    /// new) in row-major order. Boards of different sizes can't be compared.
+    pub fn diff(&self, other: &Board<T>) -> Result<Vec<(usize, usize, T, T)>, TicTacToeError>
+    where
+        T: Clone + PartialEq,
+    {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(TicTacToeError::InvalidBoard(format!(
+                "can't compare a {}x{} board with a {}x{} one",
+                self.width, self.height, other.width, other.height
+            )));
+        }
+        Ok(self
+            .cells()
+            .zip(other.positions.iter())
+            .filter(|((_, _, old), new)| old != new)
+            .map(|((x, y, old), new)| (x, y, old.clone(), new.clone()))
+            .collect())
+    }
+
+    fn index(&self, Position { x, y }: Position) -> Result<usize, TicTacToeError> {
+        if x >= self.width || y >= self.height {
+            return Err(TicTacToeError::OutOfBounds(x, y));
+        }
+        Ok(y * self.width + x)
+    }
+
+    /// Rotates the board a quarter turn clockwise, which swaps the width and
+    /// height of a rectangular board.
+    pub fn rotate_90(&self) -> Self
+    where
+        T: Clone,
+    {
+        let (width, height) = (self.width, self.height);
+        Board {
+            width: height,
+            height: width,
+            positions: (0..width * height)
+                .map(|i| self.positions[(height - 1 - i % height) * width + i / height].clone())
+                .collect(),
+        }
+    }
+
+    /// Mirrors the board left to right.
+    pub fn mirror_horizontal(&self) -> Self
+    where
+        T: Clone,
+    {
+        let width = self.width;
+        Board {
+            width,
+            height: self.height,
+            positions: (0..width * self.height)
+                .map(|i| self.positions[(i / width) * width + (width - 1 - i % width)].clone())
+                .collect(),
+        }
+    }
+
+    /// Returns the lexicographically smallest of the board's 8 rotations and
+    /// reflections, so that symmetric positions share one representative.
+    /// A rectangular board may come back turned on its side.
+    pub fn canonical(&self) -> Self
+    where
+        T: Clone + Ord,
+    {
+        let mut variants = Vec::with_capacity(8);
+        for start in [self.clone(), self.mirror_horizontal()] {
+            let mut board = start;
+            for _ in 0..4 {
+                let next = board.rotate_90();
+                variants.push(board);
+                board = next;
+            }
+        }
+        variants
+            .into_iter()
+            .min_by(|a, b| (a.width, &a.positions).cmp(&(b.width, &b.positions)))
+            .expect("there are always 8 symmetric variants")
+    }
+
+    /// The cells touching (x, y), diagonals included, with their contents
+    /// in row-major order. Edge cells have five neighbours and corners three,
+    /// while a cell off the board has none.
+    pub fn neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize, T)>
+    where
+        T: Clone,
+    {
+        if x >= self.width || y >= self.height {
+            return Vec::new();
+        }
+        let rows = y.saturating_sub(1)..(y + 2).min(self.height);
+        let columns = x.saturating_sub(1)..(x + 2).min(self.width);
+        rows.flat_map(|ny| columns.clone().map(move |nx| (nx, ny)))
+            .filter(|&cell| cell != (x, y))
+            .map(|(nx, ny)| (nx, ny, self.positions[ny * self.width + nx].clone()))
+            .collect()
+    }
+
+    /// Whether (x, y) is the middle cell. Only boards with an odd width and
+    /// height have one.
+    pub fn is_center(&self, x: usize, y: usize) -> bool {
+        self.width % 2 == 1 && self.height % 2 == 1 && x == self.width / 2 && y == self.height / 2
+    }
+
+    pub fn is_corner(&self, x: usize, y: usize) -> bool {
+        let last_x = self.width.wrapping_sub(1);
+        let last_y = self.height.wrapping_sub(1);
+        x < self.width && y < self.height && (x == 0 || x == last_x) && (y == 0 || y == last_y)
+    }
+
+    /// Whether (x, y) lies on the border of the board without being a corner.
+    pub fn is_edge(&self, x: usize, y: usize) -> bool {
+        x < self.width
+            && y < self.height
+            && (x == 0 || x == self.width - 1 || y == 0 || y == self.height - 1)
+            && !self.is_corner(x, y)
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn is_consistent(&mut self) -> bool {
+        if self.height == 0 {
+            self.height = self.width;
+        }
+        self.width > 0 && self.positions.len() == self.width * self.height
+    }
+}
+
+impl Board {
+    pub fn new() -> Self {
+        Board::with_size(DEFAULT_BOARD_WIDTH)
+    }
+
+    pub fn with_size(width: usize) -> Self {
+        Board::filled(width, None)
+    }
+
+    /// An empty board `width` cells across and `height` rows down.
+    pub fn with_dimensions(width: usize, height: usize) -> Self {
+        Board::filled_rect(width, height, None)
+    }
+
+    pub fn set_piece(
+        &self,
+        position: impl Into<Position>,
+        piece: Piece,
+    ) -> Result<Self, TicTacToeError> {
+        let mut board = self.clone();
+        board.set_piece_mut(position, piece)?;
+        Ok(board)
+    }
+
+    pub fn set_piece_mut(
+        &mut self,
+        position: impl Into<Position>,
+        piece: Piece,
+    ) -> Result<(), TicTacToeError> {
+        let position = position.into();
+        if let Some(_piece) = self.get_position(position)? {
+            return Err(TicTacToeError::PieceInPosition(position.x, position.y));
+        }
+        self.set_position_mut(position, Some(piece))
+    }
+
+    /// Plays each (x, y, piece) in turn, as `set_piece` would. The first move that
+    /// fails is returned as a `MoveFailed` error with its index in `moves`.
+    pub fn apply_moves(&self, moves: &[(usize, usize, Piece)]) -> Result<Self, TicTacToeError> {
+        let mut board = self.clone();
+        for (i, &(x, y, piece)) in moves.iter().enumerate() {
+            board
+                .set_piece_mut((x, y), piece)
+                .map_err(|e| TicTacToeError::MoveFailed(i, Box::new(e)))?;
+        }
+        Ok(board)
+    }
+
+    /// Drops `piece` into `column` so that it lands on the lowest empty row,
+    /// returning the new board and the landing row.
+    pub fn drop_piece(&self, column: usize, piece: Piece) -> Result<(Self, usize), TicTacToeError> {
+        if column >= self.width {
+            return Err(TicTacToeError::OutOfBounds(column, 0));
+        }
+        let row = (0..self.height)
+            .rev()
+            .find(|&y| self.positions[y * self.width + column].is_none())
+            .ok_or(TicTacToeError::ColumnFull(column))?;
+        Ok((self.set_piece((column, row), piece)?, row))
+    }
+
+    pub fn clear_position(&self, position: impl Into<Position>) -> Result<Self, TicTacToeError> {
+        let mut board = self.clone();
+        board.clear_position_mut(position)?;
+        Ok(board)
+    }
+
+    pub fn clear_position_mut(
+        &mut self,
+        position: impl Into<Position>,
+    ) -> Result<(), TicTacToeError> {
+        self.set_position_mut(position, None)
+    }
+
+    pub fn empty_positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.positions
+            .iter()
+            .enumerate()
+            .filter(|(_i, position)| position.is_none())
+            .map(move |(i, _position)| (i % self.width, i / self.width))
+    }
+
+    pub fn occupied_positions(&self) -> impl Iterator<Item = (usize, usize, Piece)> + '_ {
+        self.positions
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, position)| {
+                position.map(|piece| (i % self.width, i / self.width, piece))
+            })
+    }
+
+    /// Hashes where the pieces are, giving the same value on every run and
+    /// platform, unlike `DefaultHasher`, so it can key a transposition table
+    /// that is saved between runs. Empty cells add nothing, so only compare
+    /// hashes of boards the same size.
+    pub fn zobrist_hash(&self) -> u64 {
+        self.positions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, position)| position.map(|piece| zobrist_key(i, piece)))
+            .fold(0, |hash, key| hash ^ key)
+    }
+
+    /// What `piece` at `position` adds to `zobrist_hash`. XOR it into a hash
+    /// after `set_piece_mut`, and again after `clear_position_mut`, to keep
+    /// the hash up to date without going over the whole board.
+    pub fn zobrist_key(
+        &self,
+        position: impl Into<Position>,
+        piece: Piece,
+    ) -> Result<u64, TicTacToeError> {
+        Ok(zobrist_key(self.index(position.into())?, piece))
+    }
+
+    /// Renders the board with the default symbols and no headers, which is
+    /// stable enough to compare against in tests.
+    pub fn render_plain(&self) -> String {
+        let mut rendered = String::new();
+        for i in 0..self.height {
+            for j in 0..self.width {
+                match self.positions[i * self.width + j] {
+                    None => rendered.push('.'),
+                    Some(piece) => rendered.push_str(&piece.to_string()),
+                }
+                rendered.push(' ');
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    /// Renders the board with column numbers across the top and row numbers
+    /// down the side, counting from `first`.
+    pub fn render_with_headers(&self, first: usize) -> String {
+        self.render_with_symbols(first, &PieceSymbols::default())
+    }
+
+    /// Like `render_with_headers`, but shows pieces and empty cells as
+    /// `symbols`.
+    pub fn render_with_symbols(&self, first: usize, symbols: &PieceSymbols) -> String {
+        let label_width = (first + self.width.max(self.height).saturating_sub(1))
+            .to_string()
+            .len()
+            .max(symbols.width());
+        let mut rendered = format!("{:w$} ", "", w = label_width);
+        for x in 0..self.width {
+            rendered.push_str(&format!("{:>w$} ", first + x, w = label_width));
+        }
+        rendered.push('\n');
+        for y in 0..self.height {
+            rendered.push_str(&format!("{:>w$} ", first + y, w = label_width));
+            for x in 0..self.width {
+                let glyph = match self.positions[y * self.width + x] {
+                    None => symbols.empty(),
+                    Some(piece) => symbols.symbol(piece),
+                };
+                rendered.push_str(&PieceSymbols::pad(glyph, label_width));
+                rendered.push(' ');
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    /// Renders the board like a printed grid, with `|` between cells and
+    /// `---+---` between rows. Empty cells are blank.
+    pub fn render_grid(&self) -> String {
+        self.render_grid_with(&PieceSymbols::default().with_empty(" "))
+    }
+
+    /// Like `render_grid`, but shows pieces and empty cells as `symbols`,
+    /// widening every cell to fit the widest one.
+    pub fn render_grid_with(&self, symbols: &PieceSymbols) -> String {
+        let cell_width = symbols.width();
+        let separator = vec!["-".repeat(cell_width + 2); self.width].join("+");
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| {
+                        let glyph = match self.positions[y * self.width + x] {
+                            None => symbols.empty(),
+                            Some(piece) => symbols.symbol(piece),
+                        };
+                        format!(" {} ", PieceSymbols::pad(glyph, cell_width))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("|")
+            })
+            .collect::<Vec<_>>()
+            .join(&format!("\n{}\n", separator))
+            + "\n"
+    }
+
+    #[cfg(feature = "color")]
+    pub fn render_colored(&self, w: &mut impl WriteColor) -> io::Result<()> {
+        self.render_colored_with(w, 0, &[], &PieceSymbols::default())
+    }
+
+    /// Like `render_colored`, but numbers the headers from `first`, renders
+    /// the `highlight`ed cells in bold and shows pieces as `symbols`.
+    #[cfg(feature = "color")]
+    pub fn render_colored_with(
+        &self,
+        w: &mut impl WriteColor,
+        first: usize,
+        highlight: &[Position],
+        symbols: &PieceSymbols,
+    ) -> io::Result<()> {
+        let label_width = (first + self.width.max(self.height).saturating_sub(1))
+            .to_string()
+            .len()
+            .max(symbols.width());
+        write!(w, "{:w$} ", "", w = label_width)?;
+        for x in 0..self.width {
+            write!(w, "{:>w$} ", first + x, w = label_width)?;
+        }
+        writeln!(w)?;
+        for y in 0..self.height {
+            write!(w, "{:>w$} ", first + y, w = label_width)?;
+            for x in 0..self.width {
+                let position = self.positions[y * self.width + x];
+                let mut spec = ColorSpec::new();
+                match position {
+                    Some(Piece::Cross) => {
+                        spec.set_fg(Some(Color::Red));
+                    }
+                    Some(Piece::Naught) => {
+                        spec.set_fg(Some(Color::Blue));
+                    }
+                    Some(Piece::Triangle) => {
+                        spec.set_fg(Some(Color::Green));
+                    }
+                    None => {}
+                }
+                spec.set_bold(highlight.contains(&Position::new(x, y)));
+                w.set_color(&spec)?;
+                let glyph = match position {
+                    None => symbols.empty(),
+                    Some(piece) => symbols.symbol(piece),
+                };
+                write!(w, "{}", PieceSymbols::pad(glyph, label_width))?;
+                w.reset()?;
+                write!(w, " ")?;
+            }
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+
+    pub fn render_highlighted(&self, cells: &[Position]) -> String {
+        self.render_highlighted_with(cells, &PieceSymbols::default())
+    }
+
+    /// Like `render_highlighted`, but shows pieces as `symbols`.
+    pub fn render_highlighted_with(&self, cells: &[Position], symbols: &PieceSymbols) -> String {
+        let cell_width = symbols.width();
+        let mut rendered = String::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let glyph = match self.positions[y * self.width + x] {
+                    None => symbols.empty(),
+                    Some(piece) => symbols.symbol(piece),
+                };
+                let glyph = PieceSymbols::pad(glyph, cell_width);
+                if cells.contains(&Position::new(x, y)) {
+                    rendered.push_str(&format!("[{}]", glyph));
+                } else {
+                    rendered.push_str(&format!(" {} ", glyph));
+                }
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    pub fn move_count(&self) -> usize {
+        self.positions
+            .iter()
+            .filter(|position| position.is_some())
+            .count()
+    }
+
+    /// How many of each piece is on the board, including a zero for every
+    /// piece that hasn't been played.
+    pub fn count_pieces(&self) -> HashMap<Piece, usize> {
+        let mut counts: HashMap<Piece, usize> = [Piece::Naught, Piece::Cross, Piece::Triangle]
+            .iter()
+            .map(|&piece| (piece, 0))
+            .collect();
+        for piece in self.positions.iter().flatten() {
+            *counts.entry(*piece).or_default() += 1;
+        }
+        counts
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.positions.len() - self.move_count()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.positions.iter().all(|position| position.is_some())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.iter().all(|position| position.is_none())
+    }
+
+    /// All diagonal runs of `length` cells going down and to the right.
+    pub fn diagonals(&self, length: usize) -> Vec<Vec<Position>> {
+        diagonal_runs(self.width, self.height, length)
+    }
+
+    /// All anti-diagonal runs of `length` cells going down and to the left.
+    pub fn antidiagonals(&self, length: usize) -> Vec<Vec<Position>> {
+        antidiagonal_runs(self.width, self.height, length)
+    }
+
+    /// Every line of `win_length` cells on the board: rows, then columns,
+    /// then both diagonal directions. The lines are generated as the
+    /// iterator is advanced rather than all up front.
+    pub fn lines(&self, win_length: usize) -> impl Iterator<Item = Vec<Position>> {
+        lines(self.width, self.height, win_length)
+    }
+
+    /// Every line of `win_length` cells on the board, collected.
+    pub fn lines_of(&self, win_length: usize) -> Vec<Vec<Position>> {
+        winning_positions(self.width, self.height, win_length)
+    }
+
+    // No line can be longer than the board's longer side.
+    fn fits(&self, win_length: usize) -> bool {
+        win_length > 0 && win_length <= self.width.max(self.height)
+    }
+
+    /// The piece with `win_length` in a row anywhere on the board, if any. No
+    /// line can be longer than the board's longer side, so a longer
+    /// `win_length` (or zero) never has a winner.
+    pub fn winner(&self, win_length: usize) -> Option<Piece> {
+        if !self.fits(win_length) {
+            return None;
+        }
+        self.lines(win_length)
+            .find_map(|line| self.line_owner(&line))
+    }
+
+    /// Whether playing `piece` at `position` would give it `win_length` in a
+    /// row. Only the lines through that cell are checked, and the cell has to
+    /// be empty and on the board.
+    pub fn move_wins(
+        &self,
+        position: impl Into<Position>,
+        piece: Piece,
+        win_length: usize,
+    ) -> Result<bool, TicTacToeError> {
+        let position = position.into();
+        let board = self.set_piece(position, piece)?;
+        if !self.fits(win_length) {
+            return Ok(false);
+        }
+        // Counts the matching pieces in a row leading away from the new one.
+        let run = |dx: isize, dy: isize| {
+            (1..)
+                .map(|k| (position.x as isize + dx * k, position.y as isize + dy * k))
+                .take_while(|&(x, y)| {
+                    (0..self.width as isize).contains(&x)
+                        && (0..self.height as isize).contains(&y)
+                        && board.positions[y as usize * self.width + x as usize] == Some(piece)
+                })
+                .count()
+        };
+        Ok([(1, 0), (0, 1), (1, 1), (1, -1)]
+            .iter()
+            .any(|&(dx, dy)| 1 + run(dx, dy) + run(-dx, -dy) >= win_length))
+    }
+
+    /// Scores the board for `perspective`: 1,000,000 if it has `win_length`
+    /// in a row, -1,000,000 if another piece does, and otherwise the number
+    /// of lines holding only its pieces minus those holding only one
+    /// opponent's. Empty and blocked lines count for nobody. The AI scores
+    /// the positions where a depth-limited search stops with this.
+    pub fn evaluate(&self, perspective: Piece, win_length: usize) -> i32 {
+        match self.winner(win_length) {
+            Some(winner) if winner == perspective => return WIN_SCORE,
+            Some(_) => return -WIN_SCORE,
+            None => {}
+        }
+        let mut score = 0;
+        for line in self.lines(win_length) {
+            let mut pieces = line
+                .iter()
+                .filter_map(|&Position { x, y }| self.positions[y * self.width + x]);
+            if let Some(first) = pieces.next() {
+                if pieces.all(|piece| piece == first) {
+                    score += if first == perspective { 1 } else { -1 };
+                }
+            }
+        }
+        score
+    }
+
+    /// How many distinct lines of `win_length` `piece` fills. Overlapping runs
+    /// each count once, so four in a row holds two lines of three.
+    pub fn completed_lines(&self, piece: Piece, win_length: usize) -> usize {
+        if !self.fits(win_length) {
+            return 0;
+        }
+        self.lines(win_length)
+            .filter(|line| self.line_owner(line) == Some(piece))
+            .count()
+    }
+
+    /// Whether nobody can win any more because every line of `win_length`
+    /// already holds two different pieces. A board with no such lines at
+    /// all is dead from the start.
+    pub fn is_dead(&self, win_length: usize) -> bool {
+        if !self.fits(win_length) {
+            return true;
+        }
+        self.lines(win_length).all(|line| {
+            let mut pieces = line
+                .iter()
+                .filter_map(|&Position { x, y }| self.positions[y * self.width + x]);
+            pieces
+                .next()
+                .is_some_and(|first| pieces.any(|piece| piece != first))
+        })
+    }
+
+    /// Empty cells where placing `piece` completes a line of `win_length`,
+    /// in row-major order.
+    pub fn winning_moves(&self, piece: Piece, win_length: usize) -> Vec<(usize, usize)> {
+        self.completing_moves(win_length, |owner| owner == piece)
+    }
+
+    /// Empty cells where any piece other than `piece` would complete a line
+    /// of `win_length`, so `piece` has to play there to avoid losing.
+    pub fn blocking_moves(&self, piece: Piece, win_length: usize) -> Vec<(usize, usize)> {
+        self.completing_moves(win_length, |owner| owner != piece)
+    }
+
+    fn completing_moves(
+        &self,
+        win_length: usize,
+        counts: impl Fn(Piece) -> bool,
+    ) -> Vec<(usize, usize)> {
+        let mut moves = [Piece::Naught, Piece::Cross, Piece::Triangle]
+            .iter()
+            .filter(|&&piece| counts(piece))
+            .flat_map(|&piece| self.threats(piece, win_length))
+            .map(|(_line, cell)| cell)
+            .collect::<Vec<_>>();
+        moves.sort_by_key(|&(x, y)| (y, x));
+        moves.dedup();
+        moves
+    }
+
+    /// Lines of `win_length` where `piece` holds every cell but one and the
+    /// last is empty, each with that empty cell, in the order `lines` gives
+    /// them. A cell that completes two lines at once appears twice, which is
+    /// what makes a fork.
+    pub fn threats(&self, piece: Piece, win_length: usize) -> Vec<Threat> {
+        if !self.fits(win_length) {
+            return Vec::new();
+        }
+        let cell = |&Position { x, y }: &Position| self.positions[y * self.width + x];
+        self.lines(win_length)
+            .filter_map(|line| {
+                let mut empty = line.iter().filter(|position| cell(position).is_none());
+                let &Position { x, y } = empty.next()?;
+                if empty.next().is_some()
+                    || line
+                        .iter()
+                        .any(|position| cell(position).is_some_and(|owner| owner != piece))
+                {
+                    return None;
+                }
+                let line = line.iter().map(|&Position { x, y }| (x, y)).collect();
+                Some((line, (x, y)))
+            })
+            .collect()
+    }
+
+    /// Whether playing `piece` at (x, y) threatens to win in two different
+    /// cells at once, so the opponent can only block one of them. Only
+    /// threats along lines through (x, y) count, and two lines completed by
+    /// the same cell are a single threat.
+    pub fn creates_fork(
+        &self,
+        x: usize,
+        y: usize,
+        piece: Piece,
+        win_length: usize,
+    ) -> Result<bool, TicTacToeError> {
+        let board = self.set_piece((x, y), piece)?;
+        let mut cells = board
+            .threats(piece, win_length)
+            .into_iter()
+            .filter(|(line, _cell)| line.contains(&(x, y)))
+            .map(|(_line, cell)| cell)
+            .collect::<Vec<_>>();
+        cells.sort_unstable();
+        cells.dedup();
+        Ok(cells.len() >= 2)
+    }
+
+    /// The piece filling every cell of `line`, if any. An empty line, or one
+    /// that reaches off the board, has no owner.
+    pub(crate) fn line_owner(&self, line: &[Position]) -> Option<Piece> {
+        let positions = line
+            .iter()
+            .map(|&position| self.get_position(position))
+            .collect::<Result<Vec<BoardPosition>, _>>()
+            .ok()?;
+        let piece = (*positions.first()?)?;
+        if positions.iter().all(|&p| p == Some(piece)) {
+            return Some(piece);
+        }
+        None
+    }
+
+    /// Checks that the position could have come from a real game in which
+    /// `players` took turns in order, starting with `first`: each player
+    /// holds exactly as many pieces as the moves played give them, and at
+    /// most one piece has completed a line of `win_length`.
+    pub fn validate(
+        &self,
+        players: &[Piece],
+        first: Piece,
+        win_length: usize,
+    ) -> Result<(), TicTacToeError> {
+        let pieces = self.count_pieces();
+        if let Some(stranger) = [Piece::Naught, Piece::Cross, Piece::Triangle]
+            .iter()
+            .find(|piece| pieces[piece] > 0 && !players.contains(piece))
+        {
+            return Err(TicTacToeError::IllegalState(format!(
+                "{} has pieces on the board but isn't playing",
+                stranger
+            )));
+        }
+        let start = players
+            .iter()
+            .position(|&player| player == first)
+            .ok_or_else(|| {
+                TicTacToeError::IllegalState(format!("{} can't start, it isn't playing", first))
+            })?;
+        let moves = self.move_count();
+        for i in 0..players.len() {
+            let player = players[(start + i) % players.len()];
+            let expected = (moves + players.len() - 1 - i) / players.len();
+            if pieces[&player] != expected {
+                return Err(TicTacToeError::IllegalState(format!(
+                    "{} has {} pieces, but should have {} after {} moves when {} started",
+                    player, pieces[&player], expected, moves, first
+                )));
+            }
+        }
+        if !self.fits(win_length) {
+            return Ok(());
+        }
+        let mut winners: Vec<Piece> = Vec::new();
+        for slice in self.lines(win_length) {
+            let first = slice
+                .first()
+                .and_then(|&Position { x, y }| self.positions[y * self.width + x]);
+            if let Some(piece) = first {
+                if slice
+                    .iter()
+                    .all(|&Position { x, y }| self.positions[y * self.width + x] == Some(piece))
+                    && !winners.contains(&piece)
+                {
+                    winners.push(piece);
+                }
+            }
+        }
+        if winners.len() > 1 {
+            return Err(TicTacToeError::IllegalState(format!(
+                "more than one player has won: {:?}",
+                winners
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Looks up the cell at `(x, y)`, like `get_position` but by reference.
+///
+/// # Panics
+///
+/// Panics if `(x, y)` is off the board. Use `get_position` to get an
+/// `OutOfBounds` error instead.
+impl<T> Index<(usize, usize)> for Board<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        match Board::index(self, Position { x, y }) {
+            Ok(index) => &self.positions[index],
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
+    }
+}
+
+impl FromStr for Board {
+    type Err = TicTacToeError;
+
+    /// Parses a grid of `X`, `O`, `T` and `.` cells, one row per line, with
+    /// every row the same length. Cells may be separated by whitespace, and
+    /// the headers printed by `Display` are skipped, so
+    /// `board.to_string().parse()` round-trips.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().filter(|line| !line.trim().is_empty()).peekable();
+        let is_number = |token: &str| token.parse::<usize>().is_ok();
+        if let Some(first) = lines.peek() {
+            if first.split_whitespace().all(is_number) {
+                lines.next();
+            }
+        }
+        let mut width = None;
+        let mut positions = Vec::new();
+        let mut rows = 0;
+        for line in lines {
+            let mut tokens = line.split_whitespace().peekable();
+            if tokens.peek().is_some_and(|token| is_number(token)) {
+                tokens.next();
+            }
+            let mut cells = 0;
+            for c in tokens.flat_map(str::chars) {
+                positions.push(match c {
+                    'X' => Some(Piece::Cross),
+                    'O' => Some(Piece::Naught),
+                    'T' => Some(Piece::Triangle),
+                    '.' => None,
+                    c => {
+                        return Err(TicTacToeError::InvalidBoard(format!(
+                            "unexpected character {:?}",
+                            c
+                        )))
+                    }
+                });
+                cells += 1;
+            }
+            if *width.get_or_insert(cells) != cells {
+                return Err(TicTacToeError::InvalidBoard(format!(
+                    "row {} has {} cells, expected {}",
+                    rows,
+                    cells,
+                    width.unwrap_or_default()
+                )));
+            }
+            rows += 1;
+        }
+        let width = width.ok_or_else(|| TicTacToeError::InvalidBoard("no rows".to_string()))?;
+        if width == 0 {
+            return Err(TicTacToeError::InvalidBoard(
+                "rows have no cells".to_string(),
+            ));
+        }
+        Ok(Board {
+            width,
+            height: rows,
+            positions,
+        })
+    }
+}
+
+impl Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render_with_headers(0))
+    }
+}
+
+/// Every run of `length` cells going down and to the right, in row-major
+/// order of their top-left cells.
+pub(crate) fn diagonal_runs(width: usize, height: usize, length: usize) -> Vec<Vec<Position>> {
+    diagonal_lines(width, height, length).collect()
+}
+
+/// Every run of `length` cells going down and to the left, in row-major
+/// order of their top-right cells.
+pub(crate) fn antidiagonal_runs(width: usize, height: usize, length: usize) -> Vec<Vec<Position>> {
+    antidiagonal_lines(width, height, length).collect()
+}
+
+// How many runs of `length` start along each side, or none either way when
+// the runs don't fit diagonally.
+fn diagonal_starts(width: usize, height: usize, length: usize) -> (usize, usize) {
+    if length == 0 || length > width || length > height {
+        return (0, 0);
+    }
+    (width - length + 1, height - length + 1)
+}
+
+fn diagonal_lines(
+    width: usize,
+    height: usize,
+    length: usize,
+) -> impl Iterator<Item = Vec<Position>> {
+    let (across, down) = diagonal_starts(width, height, length);
+    (0..down).flat_map(move |y| {
+        (0..across).map(move |x| (0..length).map(|k| Position::new(x + k, y + k)).collect())
+    })
+}
+
+fn antidiagonal_lines(
+    width: usize,
+    height: usize,
+    length: usize,
+) -> impl Iterator<Item = Vec<Position>> {
+    let (across, down) = diagonal_starts(width, height, length);
+    (0..down).flat_map(move |y| {
+        (length.saturating_sub(1)..length.saturating_sub(1) + across)
+            .map(move |x| (0..length).map(|k| Position::new(x - k, y + k)).collect())
+    })
+}
+
+/// Every row, column and diagonal run of `win_length` cells on a board
+/// `width` across and `height` down, generated one at a time. Runs only fit
+/// along the sides they are no longer than, so a long line on a narrow
+/// board can only lie one way.
+pub(crate) fn lines(
+    width: usize,
+    height: usize,
+    win_length: usize,
+) -> impl Iterator<Item = Vec<Position>> {
+    let runs = move |side: usize| (side + 1).saturating_sub(win_length);
+    let sides = if win_length == 0 {
+        0
+    } else {
+        width.max(height)
+    };
+    (0..sides)
+        .flat_map(move |i| {
+            (0..runs(width).max(runs(height))).flat_map(move |j| {
+                let row = (i < height && j < runs(width))
+                    .then(|| (0..win_length).map(|k| Position::new(j + k, i)).collect());
+                let column = (i < width && j < runs(height))
+                    .then(|| (0..win_length).map(|k| Position::new(i, j + k)).collect());
+                row.into_iter().chain(column)
+            })
+        })
+        .chain(diagonal_lines(width, height, win_length))
+        .chain(antidiagonal_lines(width, height, win_length))
+}
+
+pub(crate) fn winning_positions(
+    width: usize,
+    height: usize,
+    win_length: usize,
+) -> Vec<Vec<Position>> {
+    lines(width, height, win_length).collect()
+}
+
+// The key for `piece` on the cell at row-major `index`, from a table built on
+// first use.
+fn zobrist_key(index: usize, piece: Piece) -> u64 {
+    let slot = index * 3
+        + match piece {
+            Piece::Naught => 0,
+            Piece::Cross => 1,
+            Piece::Triangle => 2,
+        };
+    ZOBRIST_KEYS
+        .get_or_init(|| (0..ZOBRIST_CELLS * 3).map(splitmix64).collect())
+        .get(slot)
+        .copied()
+        .unwrap_or_else(|| splitmix64(slot))
+}
+
+// SplitMix64 run from a fixed seed, written out here so the keys never
+// change with a dependency's version.
+fn splitmix64(slot: usize) -> u64 {
+    let mut z = (slot as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const PLAYERS: [Piece; 2] = [Piece::Naught, Piece::Cross];
+
+    fn parse(rows: &str) -> Board {
+        rows.parse().unwrap()
+    }
+
+    #[test]
+    fn grids_show_the_empty_glyph() {
+        let board = parse("X..\n.O.\n...");
+        let grid = board.render_grid_with(&PieceSymbols::default().with_empty("_"));
+        assert_eq!(grid.lines().next(), Some(" X | _ | _ "));
+        assert_eq!(board.render_grid().lines().next(), Some(" X |   |   "));
+    }
+
+    #[test]
+    fn winning_positions_cover_every_line_once() {
+        for width in 1..=6 {
+            for height in 1..=6 {
+                for win_length in 1..=7 {
+                    let runs = |side: usize| (side + 1).saturating_sub(win_length);
+                    let slices = winning_positions(width, height, win_length);
+                    // Every row holds `runs(width)` runs and every column
+                    // `runs(height)`, and each diagonal direction one per pair
+                    // of them: 2N + 2 slices when the board is N square and
+                    // k == N.
+                    assert_eq!(
+                        slices.len(),
+                        height * runs(width)
+                            + width * runs(height)
+                            + 2 * runs(width) * runs(height)
+                    );
+                    for slice in &slices {
+                        assert_eq!(slice.len(), win_length);
+                        assert!(slice
+                            .iter()
+                            .all(|&Position { x, y }| x < width && y < height));
+                        assert!(slice
+                            .iter()
+                            .enumerate()
+                            .all(|(i, position)| !slice[..i].contains(position)));
+                        // Each step along the line is the same one square
+                        // right, down, down-right or down-left.
+                        let step = |i: usize| {
+                            let (from, to) = (slice[i], slice[i + 1]);
+                            (
+                                to.x as isize - from.x as isize,
+                                to.y as isize - from.y as isize,
+                            )
+                        };
+                        if win_length > 1 {
+                            assert!([(1, 0), (0, 1), (1, 1), (-1, 1)].contains(&step(0)));
+                            assert!((1..win_length - 1).all(|i| step(i) == step(0)));
+                        }
+                    }
+                    // A single cell is a line in every direction, so only
+                    // longer lines have to be distinct.
+                    if win_length > 1 {
+                        let distinct = slices
+                            .iter()
+                            .map(|slice| {
+                                let mut cells = slice.clone();
+                                cells.sort();
+                                cells
+                            })
+                            .collect::<HashSet<_>>();
+                        assert_eq!(distinct.len(), slices.len());
+                    }
+                }
+            }
+        }
+        let slices = winning_positions(3, 3, 3);
+        let line = |cells: &[(usize, usize)]| {
+            cells
+                .iter()
+                .map(|&(x, y)| Position::new(x, y))
+                .collect::<Vec<_>>()
+        };
+        assert!(slices.contains(&line(&[(0, 0), (1, 1), (2, 2)])));
+        assert!(slices.contains(&line(&[(2, 0), (1, 1), (0, 2)])));
+    }
+
+    #[test]
+    fn five_crosses_and_one_naught_is_illegal() {
+        let board = parse("XXX\nXX.\nO..");
+        assert!(board.validate(&PLAYERS, Piece::Naught, 3).is_err());
+        assert!(board.validate(&PLAYERS, Piece::Cross, 3).is_err());
+    }
+
+    #[test]
+    fn a_mid_game_board_is_legal() {
+        let board = parse("OX.\n.O.\nX..");
+        assert!(board.validate(&PLAYERS, Piece::Naught, 3).is_ok());
+        assert!(board.validate(&PLAYERS, Piece::Cross, 3).is_ok());
+        let board = parse("OX.\n.O.\n...");
+        assert!(board.validate(&PLAYERS, Piece::Naught, 3).is_ok());
+    }
+
+    #[test]
+    fn the_second_player_cant_be_ahead() {
+        let board = parse("XX.\n.O.\n...");
+        assert!(board.validate(&PLAYERS, Piece::Naught, 3).is_err());
+        assert!(board.validate(&PLAYERS, Piece::Cross, 3).is_ok());
+    }
+
+    #[test]
+    fn empty_positions_cover_an_empty_board_and_nothing_on_a_full_one() {
+        let empty = Board::new().empty_positions().collect::<Vec<_>>();
+        let every_cell = (0..3)
+            .flat_map(|y| (0..3).map(move |x| (x, y)))
+            .collect::<Vec<_>>();
+        assert_eq!(empty, every_cell);
+        let full = parse("OXO\nXOX\nXOX");
+        assert_eq!(full.empty_positions().count(), 0);
+    }
+
+    #[test]
+    fn identical_boards_are_equal_and_hash_alike() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let hash = |board: &Board| {
+            let mut hasher = DefaultHasher::new();
+            board.hash(&mut hasher);
+            hasher.finish()
+        };
+        let a = Board::new().set_piece((1, 1), Piece::Naught).unwrap();
+        let b = parse("...\n.O.\n...");
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        let c = b.set_piece((0, 0), Piece::Cross).unwrap();
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn four_quarter_turns_give_back_the_board() {
+        for board in &[parse("OX.\n..X\n.O."), parse("OX.\n..T")] {
+            let turned = board.rotate_90().rotate_90().rotate_90().rotate_90();
+            assert_eq!(&turned, board);
+            assert_eq!(
+                board.rotate_90().dimensions(),
+                (board.height(), board.width())
+            );
+        }
+    }
+
+    #[test]
+    fn symmetric_boards_share_a_canonical_form() {
+        for board in &[parse("OX.\n..X\n.O."), parse("OX.\n..T")] {
+            let mut variants = Vec::new();
+            for start in [board.clone(), board.mirror_horizontal()] {
+                let mut variant = start;
+                for _ in 0..4 {
+                    variants.push(variant.clone());
+                    variant = variant.rotate_90();
+                }
+            }
+            let canonical = board.canonical();
+            assert!(variants.contains(&canonical));
+            assert!(variants
+                .iter()
+                .all(|variant| variant.canonical() == canonical));
+        }
+    }
+
+    #[test]
+    fn a_3x3_board_has_one_center_four_corners_and_four_edges() {
+        let board = Board::new();
+        let count = |is: fn(&Board, usize, usize) -> bool| {
+            board
+                .empty_positions()
+                .filter(|&(x, y)| is(&board, x, y))
+                .count()
+        };
+        assert_eq!(count(Board::is_center), 1);
+        assert_eq!(count(Board::is_corner), 4);
+        assert_eq!(count(Board::is_edge), 4);
+        assert!(board.is_center(1, 1));
+        assert!(!board.is_corner(3, 0));
+        assert!(!board.is_edge(1, 3));
+
+        let even = Board::with_size(4);
+        assert!(even.empty_positions().all(|(x, y)| !even.is_center(x, y)));
+    }
+
+    #[test]
+    fn two_in_a_row_has_one_winning_and_blocking_move() {
+        let board = parse("XX.\n.O.\n...");
+        assert_eq!(board.winning_moves(Piece::Cross, 3), vec![(2, 0)]);
+        assert_eq!(board.blocking_moves(Piece::Naught, 3), vec![(2, 0)]);
+        assert!(board.winning_moves(Piece::Naught, 3).is_empty());
+        assert!(board.blocking_moves(Piece::Cross, 3).is_empty());
+    }
+
+    #[test]
+    fn a_4x4_board_has_the_known_number_of_diagonals() {
+        let board = Board::with_size(4);
+        for &(length, runs) in &[(1, 16), (2, 9), (3, 4), (4, 1), (5, 0)] {
+            assert_eq!(board.diagonals(length).len(), runs, "length {}", length);
+            assert_eq!(board.antidiagonals(length).len(), runs, "length {}", length);
+        }
+        let main = (0..4).map(|i| Position::new(i, i)).collect::<Vec<_>>();
+        let anti = (0..4).map(|i| Position::new(3 - i, i)).collect::<Vec<_>>();
+        assert_eq!(board.diagonals(4), vec![main]);
+        assert_eq!(board.antidiagonals(4), vec![anti]);
+    }
+
+    #[test]
+    fn evaluate_scores_wins_losses_and_open_lines() {
+        let won = parse("OOO\nXX.\n...");
+        assert_eq!(won.evaluate(Piece::Naught, 3), WIN_SCORE);
+        assert_eq!(won.evaluate(Piece::Cross, 3), -WIN_SCORE);
+        // Naught's centre has three open lines, and Cross's edge has one.
+        let mid_game = parse(".X.\n.O.\n...");
+        assert_eq!(mid_game.evaluate(Piece::Naught, 3), 2);
+        assert_eq!(mid_game.evaluate(Piece::Cross, 3), -2);
+        assert_eq!(Board::new().evaluate(Piece::Naught, 3), 0);
+    }
+
+    #[test]
+    fn every_line_contested_is_dead() {
+        let board = parse("XOX\nXOO\nOX.");
+        assert!(board.is_dead(3));
+        assert!(!board.is_full());
+        assert!(!parse("XOX\nXOO\n...").is_dead(3));
+    }
+
+    #[test]
+    fn neighbours_stop_at_the_edges() {
+        let board = parse("OX.\n.T.\n...");
+        assert_eq!(
+            board.neighbors(0, 0),
+            vec![
+                (1, 0, Some(Piece::Cross)),
+                (0, 1, None),
+                (1, 1, Some(Piece::Triangle)),
+            ]
+        );
+        let centre = board.neighbors(1, 1);
+        assert_eq!(centre.len(), 8);
+        assert!(!centre.iter().any(|&(x, y, _)| (x, y) == (1, 1)));
+        assert_eq!(board.neighbors(1, 0).len(), 5);
+        assert!(board.neighbors(3, 3).is_empty());
+    }
+
+    #[test]
+    fn count_pieces_counts_each_piece() {
+        let empty = Board::new().count_pieces();
+        assert!([Piece::Naught, Piece::Cross, Piece::Triangle]
+            .iter()
+            .all(|piece| empty[piece] == 0));
+        let counts = parse("OX.\nXO.\nO..").count_pieces();
+        assert_eq!(counts[&Piece::Naught], 3);
+        assert_eq!(counts[&Piece::Cross], 2);
+        assert_eq!(counts[&Piece::Triangle], 0);
+    }
+
+    #[test]
+    fn move_wins_checks_one_candidate() {
+        let board = parse("XX.\n.O.\nO..");
+        assert!(board.move_wins((2, 0), Piece::Cross, 3).unwrap());
+        assert!(!board.move_wins((2, 2), Piece::Cross, 3).unwrap());
+        assert!(board.move_wins((2, 0), Piece::Naught, 3).unwrap());
+        assert!(matches!(
+            board.move_wins((0, 0), Piece::Naught, 3),
+            Err(TicTacToeError::PieceInPosition(0, 0))
+        ));
+        assert!(matches!(
+            board.move_wins((3, 0), Piece::Cross, 3),
+            Err(TicTacToeError::OutOfBounds(3, 0))
+        ));
+    }
+
+    #[test]
+    fn diff_lists_the_changed_cell() {
+        let before = parse("O..\n...\n...");
+        let after = before.set_piece((2, 1), Piece::Cross).unwrap();
+        assert_eq!(
+            before.diff(&after).unwrap(),
+            vec![(2, 1, None, Some(Piece::Cross))]
+        );
+        assert!(before.diff(&before).unwrap().is_empty());
+        assert!(matches!(
+            before.diff(&Board::with_size(4)),
+            Err(TicTacToeError::InvalidBoard(_))
+        ));
+    }
+
+    #[test]
+    fn lazy_lines_match_the_eager_3x3_lines() {
+        // The rows, columns and two diagonals the game started out with.
+        let n = 3;
+        let eager = (0..n)
+            .map(|y| (0..n).map(|x| Position::new(x, y)).collect::<Vec<_>>())
+            .chain((0..n).map(|x| (0..n).map(|y| Position::new(x, y)).collect()))
+            .chain(vec![
+                (0..n).map(|i| Position::new(i, i)).collect(),
+                (0..n).map(|i| Position::new(n - 1 - i, i)).collect(),
+            ])
+            .collect::<HashSet<_>>();
+        let lazy = Board::new().lines(3).collect::<Vec<_>>();
+        assert_eq!(lazy.len(), 2 * n + 2);
+        assert_eq!(lazy.into_iter().collect::<HashSet<_>>(), eager);
+    }
+
+    #[test]
+    fn zobrist_hashes_update_by_xor() {
+        let a = parse("OX.\n.O.\n...");
+        let b = Board::new()
+            .apply_moves(&[
+                (0, 0, Piece::Naught),
+                (1, 0, Piece::Cross),
+                (1, 1, Piece::Naught),
+            ])
+            .unwrap();
+        assert_eq!(a.zobrist_hash(), b.zobrist_hash());
+
+        let mut board = a.clone();
+        let mut hash = board.zobrist_hash();
+        board.set_piece_mut((2, 2), Piece::Cross).unwrap();
+        hash ^= board.zobrist_key((2, 2), Piece::Cross).unwrap();
+        assert_eq!(hash, board.zobrist_hash());
+        assert_ne!(hash, a.zobrist_hash());
+        board.clear_position_mut((2, 2)).unwrap();
+        hash ^= board.zobrist_key((2, 2), Piece::Cross).unwrap();
+        assert_eq!(hash, board.zobrist_hash());
+        assert_eq!(hash, a.zobrist_hash());
+    }
+
+    #[test]
+    fn apply_moves_reports_the_failing_move() {
+        let moves = [
+            (0, 0, Piece::Naught),
+            (1, 1, Piece::Cross),
+            (2, 2, Piece::Naught),
+        ];
+        assert_eq!(
+            Board::new().apply_moves(&moves).unwrap(),
+            parse("O..\n.X.\n..O")
+        );
+        let colliding = [
+            (0, 0, Piece::Naught),
+            (1, 1, Piece::Cross),
+            (0, 0, Piece::Naught),
+        ];
+        match Board::new().apply_moves(&colliding) {
+            Err(TicTacToeError::MoveFailed(2, e)) => {
+                assert!(matches!(*e, TicTacToeError::PieceInPosition(0, 0)))
+            }
+            other => panic!("expected the third move to fail, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_fork_is_two_threats_with_different_cells() {
+        let board = parse("X.O\n.O.\nX.X");
+        let mut threats = board.threats(Piece::Cross, 3);
+        threats.sort_by_key(|&(_, cell)| cell);
+        assert_eq!(
+            threats,
+            vec![
+                (vec![(0, 0), (0, 1), (0, 2)], (0, 1)),
+                (vec![(0, 2), (1, 2), (2, 2)], (1, 2)),
+            ]
+        );
+        assert!(board.threats(Piece::Naught, 3).is_empty());
+    }
+
+    #[test]
+    fn the_corner_opening_trap_is_a_fork() {
+        // X opens in a corner, O answers on an edge, X takes the centre and
+        // O blocks the diagonal. The other corner on X's column now forks.
+        let board = Board::new()
+            .apply_moves(&[
+                (0, 0, Piece::Cross),
+                (1, 0, Piece::Naught),
+                (1, 1, Piece::Cross),
+                (2, 2, Piece::Naught),
+            ])
+            .unwrap();
+        assert!(board.creates_fork(0, 2, Piece::Cross, 3).unwrap());
+        assert!(!board.creates_fork(2, 1, Piece::Cross, 3).unwrap());
+        assert!(board.creates_fork(1, 1, Piece::Cross, 3).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use core::fmt::{self, Display};
+use std::error::Error;
+use std::io;
+
+use crate::piece::Piece;
+
+#[derive(Debug)]
+pub enum TicTacToeError {
+    OutOfBounds(usize, usize),
+    PieceInPosition(usize, usize),
+    ColumnFull(usize),
+    InvalidMoveInput,
+    NothingToUndo,
+    NothingToRedo,
+    GameOver,
+    OutOfTurn(Piece),
+    IllegalState(String),
+    Io(io::Error),
+    InvalidSaveFile(String),
+    InvalidBoard(String),
+    InvalidMoveFile(usize),
+    TimedOut,
+    InvalidNotation(String),
+    SwapNotAllowed,
+    MoveFailed(usize, Box<TicTacToeError>),
+}
+
+impl Display for TicTacToeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TicTacToeError::OutOfBounds(x, y) => {
+                write!(f, "position ({}, {}) is out of bounds", x, y)
+            }
+            TicTacToeError::PieceInPosition(x, y) => {
+                write!(f, "a piece already occupies ({}, {})", x, y)
+            }
+            TicTacToeError::ColumnFull(column) => write!(f, "column {} is full", column),
+            TicTacToeError::InvalidMoveInput => write!(f, "could not parse move input"),
+            TicTacToeError::NothingToUndo => write!(f, "there are no moves to undo"),
+            TicTacToeError::NothingToRedo => write!(f, "there are no moves to redo"),
+            TicTacToeError::GameOver => write!(f, "the game is already over"),
+            TicTacToeError::OutOfTurn(piece) => write!(f, "it is not {}'s turn", piece),
+            TicTacToeError::IllegalState(reason) => write!(f, "illegal position: {}", reason),
+            TicTacToeError::Io(e) => write!(f, "{}", e),
+            TicTacToeError::InvalidSaveFile(reason) => write!(f, "invalid save file: {}", reason),
+            TicTacToeError::InvalidBoard(reason) => write!(f, "invalid board: {}", reason),
+            TicTacToeError::InvalidMoveFile(line) => {
+                write!(f, "could not parse line {} of the move file", line)
+            }
+            TicTacToeError::TimedOut => write!(f, "ran out of time to move"),
+            TicTacToeError::InvalidNotation(token) => {
+                write!(f, "could not parse \"{}\" in the game notation", token)
+            }
+            TicTacToeError::SwapNotAllowed => {
+                write!(f, "sides can only be swapped once, on move two")
+            }
+            TicTacToeError::MoveFailed(index, e) => {
+                write!(f, "the move at index {} failed: {}", index, e)
+            }
+        }
+    }
+}
+
+impl Error for TicTacToeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TicTacToeError::Io(e) => Some(e),
+            TicTacToeError::MoveFailed(_, e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TicTacToeError {
+    fn from(e: io::Error) -> Self {
+        TicTacToeError::Io(e)
+    }
+}
@@ -0,0 +1,2023 @@
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::io::IsTerminal;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{fs, path::Path};
+
+use log::{debug, info, trace};
+#[cfg(feature = "rand")]
+use rand::{rngs::StdRng, Rng, SeedableRng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "color")]
+use termcolor::{ColorChoice, StandardStream};
+
+use crate::ai::{AiPlayer, Difficulty};
+use crate::board::{winning_positions, Board};
+use crate::error::TicTacToeError;
+use crate::input::{parse_cell_number, parse_move, parse_move_record, parse_piece};
+use crate::observer::GameObserver;
+use crate::piece::Piece;
+use crate::position::Position;
+use crate::symbols::PieceSymbols;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Place(usize, usize),
+    Undo,
+    Redo,
+    Hint,
+    Status,
+    Swap,
+    OfferDraw,
+    Resign,
+    Quit,
+}
+
+pub type Move = (Piece, usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    InProgress,
+    Win(Piece),
+    Draw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    Win(Piece),
+    Draw,
+    Aborted,
+}
+
+/// How a finished game ended, from `Game::into_result`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOutcome {
+    Win(Piece),
+    Draw,
+}
+
+/// The error from `Game::into_result` when the game isn't over yet. It hands
+/// the game back so it can be played on.
+#[derive(Debug)]
+pub struct GameInProgress(pub Box<Game>);
+
+impl fmt::Display for GameInProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the game is still in progress at move {}",
+            self.0.state.board.move_count() + 1
+        )
+    }
+}
+
+impl Error for GameInProgress {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalMovePolicy {
+    Skip,
+    Abort,
+}
+
+/// Who makes the moves for a piece in `run`.
+#[derive(Debug, Clone, Copy)]
+pub enum Player {
+    Human,
+    Ai(AiPlayer),
+}
+
+/// How `run` shows the board each turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Human,
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GameState {
+    pub board: Board,
+    pub turn: Piece,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub move_number: usize,
+}
+
+// What `save` writes: the state, plus the rules it is played under. Saves
+// from before the rules were included only have the state, and load with
+// the default rules for their board.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct SaveFile {
+    #[serde(flatten)]
+    state: GameState,
+    #[serde(default)]
+    win_length: Option<usize>,
+    #[serde(default)]
+    players: Option<Vec<Piece>>,
+}
+
+impl GameState {
+    /// Plays the current turn's piece at (x, y) and hands the turn to
+    /// whoever follows it in `players`, the same way `Game` takes turns.
+    pub fn place(
+        &self,
+        x: usize,
+        y: usize,
+        players: &[Piece],
+    ) -> Result<GameState, TicTacToeError> {
+        Ok(GameState {
+            board: self.board.set_piece((x, y), self.turn)?,
+            turn: next_player(players, self.turn),
+            move_number: self.move_number + 1,
+        })
+    }
+}
+
+// Writes a line of `run`'s messages to wherever the game sends them. Output
+// is best effort like `println!`, except that a closed pipe doesn't panic.
+macro_rules! say {
+    ($game:expr) => {{
+        let _ = writeln!($game.messages());
+    }};
+    ($game:expr, $($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        let _ = writeln!($game.messages(), "{}", message);
+    }};
+}
+
+/// Where `run` writes the board and its messages.
+enum Output {
+    Stdout(io::Stdout),
+    Custom(Box<dyn Write>),
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(stdout) => stdout.write(buf),
+            Output::Custom(output) => output.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(stdout) => stdout.flush(),
+            Output::Custom(output) => output.flush(),
+        }
+    }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Output::Stdout(_) => write!(f, "Stdout"),
+            Output::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Game {
+    state: GameState,
+    winning_positions: Vec<Vec<Position>>,
+    win_length: usize,
+    players: Vec<Piece>,
+    controllers: HashMap<Piece, Player>,
+    ai_delay: Duration,
+    ai_games: usize,
+    one_based: bool,
+    cell_numbers: bool,
+    reverse: bool,
+    analysis: bool,
+    pie_rule: bool,
+    swapped: bool,
+    redraw: bool,
+    symbols: PieceSymbols,
+    illegal_move_policy: IllegalMovePolicy,
+    output_format: OutputFormat,
+    history: Vec<GameState>,
+    moves: Vec<Move>,
+    undone: Vec<Move>,
+    // The line that first won the game. Analysis mode can complete more
+    // lines afterwards, and they mustn't take the win away.
+    first_win: Option<(Piece, Vec<Position>)>,
+    wins_naught: u32,
+    wins_cross: u32,
+    wins_triangle: u32,
+    draws: u32,
+    observer: Option<Box<dyn GameObserver>>,
+    output: Output,
+    move_timeout: Option<Duration>,
+    turn_deadline: Option<Instant>,
+    stdin_lines: Option<Receiver<io::Result<String>>>,
+    batch: VecDeque<(usize, usize, usize)>,
+    batch_pair: Option<usize>,
+    #[cfg(feature = "rand")]
+    first_player_rng: Option<StdRng>,
+}
+
+impl Game {
+    /// Creates a game on a `size`-wide board, or the default 3x3 one. Naught
+    /// always moves first unless `with_random_start` is used, which needs
+    /// the `rand` feature.
+    pub fn new(size: Option<usize>) -> Self {
+        Game::on_board(size.map_or_else(Board::new, Board::with_size))
+    }
+
+    /// Creates a game on a board `width` cells across and `height` rows
+    /// down. Lines have to be as long as the shorter side to win.
+    pub fn new_rectangular(width: usize, height: usize) -> Self {
+        Game::on_board(Board::with_dimensions(width, height))
+    }
+
+    fn on_board(board: Board) -> Self {
+        let (width, height) = (board.width(), board.height());
+        let win_length = width.min(height);
+        Game {
+            state: GameState {
+                board,
+                turn: Piece::Naught,
+                move_number: 0,
+            },
+            winning_positions: winning_positions(width, height, win_length),
+            win_length,
+            players: vec![Piece::Naught, Piece::Cross],
+            controllers: HashMap::new(),
+            ai_delay: Duration::from_millis(500),
+            ai_games: 1,
+            one_based: false,
+            cell_numbers: false,
+            reverse: false,
+            analysis: false,
+            pie_rule: false,
+            swapped: false,
+            redraw: false,
+            symbols: PieceSymbols::default(),
+            illegal_move_policy: IllegalMovePolicy::Abort,
+            output_format: OutputFormat::Human,
+            history: Vec::new(),
+            moves: Vec::new(),
+            undone: Vec::new(),
+            first_win: None,
+            wins_naught: 0,
+            wins_cross: 0,
+            wins_triangle: 0,
+            draws: 0,
+            observer: None,
+            output: Output::Stdout(io::stdout()),
+            move_timeout: None,
+            turn_deadline: None,
+            stdin_lines: None,
+            batch: VecDeque::new(),
+            batch_pair: None,
+            #[cfg(feature = "rand")]
+            first_player_rng: None,
+        }
+    }
+
+    /// Lets the computer play `ai.piece()`.
+    pub fn with_ai(self, ai: AiPlayer) -> Self {
+        self.with_player(ai.piece(), Player::Ai(ai))
+    }
+
+    /// Decides who moves for `piece`. Pieces are played by humans unless
+    /// set otherwise.
+    pub fn with_player(mut self, piece: Piece, player: Player) -> Self {
+        self.controllers.insert(piece, player);
+        self
+    }
+
+    pub fn player(&self, piece: Piece) -> Player {
+        self.controllers
+            .get(&piece)
+            .copied()
+            .unwrap_or(Player::Human)
+    }
+
+    /// How long `run` waits before each computer move when nobody human is
+    /// playing, so the game can be followed.
+    pub fn with_ai_delay(mut self, delay: Duration) -> Self {
+        self.ai_delay = delay;
+        self
+    }
+
+    /// How many games `run` plays when nobody human is playing, before
+    /// printing the final tally. Defaults to one.
+    pub fn with_ai_games(mut self, games: usize) -> Self {
+        self.ai_games = games.max(1);
+        self
+    }
+
+    /// Reads and prints coordinates starting at 1 instead of 0.
+    pub fn with_one_based(mut self, one_based: bool) -> Self {
+        self.one_based = one_based;
+        self
+    }
+
+    /// Also accepts a single number naming a cell, counting 1 to width² from
+    /// the top-left across each row, like a phone keypad.
+    pub fn with_cell_numbers(mut self, cell_numbers: bool) -> Self {
+        self.cell_numbers = cell_numbers;
+        self
+    }
+
+    /// Gives each human player `timeout` to make their move in `run`, after
+    /// which their turn passes to the next player.
+    pub fn with_move_timeout(mut self, timeout: Duration) -> Self {
+        self.move_timeout = Some(timeout);
+        self
+    }
+
+    /// Plays the misère variant, where completing a line loses and the win
+    /// goes to the next player instead. The AI and hints play to avoid lines.
+    pub fn with_reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Keeps accepting moves in `run` after a line is completed, until the
+    /// board fills up, with the first completed line marked on the board.
+    pub fn with_analysis(mut self, analysis: bool) -> Self {
+        self.analysis = analysis;
+        self
+    }
+
+    /// Clears the terminal before each board so it updates in place instead of
+    /// scrolling. This only happens when printing to a terminal, so piped
+    /// output and custom outputs keep every board.
+    pub fn with_redraw(mut self, redraw: bool) -> Self {
+        self.redraw = redraw;
+        self
+    }
+
+    /// Plays with the pie rule: after the opening move, the second player may
+    /// type `swap` to take over the opening piece instead of replying. The
+    /// swap is only offered once, on move two, and only in two-player games.
+    pub fn with_pie_rule(mut self, pie_rule: bool) -> Self {
+        self.pie_rule = pie_rule;
+        self
+    }
+
+    /// Shows pieces as `symbols` on the board and in messages. Moves are still
+    /// entered as coordinates.
+    pub fn with_symbols(mut self, symbols: PieceSymbols) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    pub fn with_observer(mut self, observer: Box<dyn GameObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Sends everything `run` prints to `output` instead of stdout, such as a
+    /// buffer to check in a test or a window in another frontend. Colours are
+    /// only used on stdout.
+    pub fn with_output(mut self, output: Box<dyn Write>) -> Self {
+        self.output = Output::Custom(output);
+        self
+    }
+
+    /// Decides whether `run_with` skips illegal moves or gives up on them.
+    pub fn with_illegal_move_policy(mut self, policy: IllegalMovePolicy) -> Self {
+        self.illegal_move_policy = policy;
+        self
+    }
+
+    /// Picks how `run` shows the board. In JSON mode only the board objects
+    /// go to the output, and every other message goes to stderr.
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
+    }
+
+    /// Sets who takes part and the order they move in, e.g. adding
+    /// `Piece::Triangle` for a three-player game on a larger board. A game
+    /// already under way keeps its turn if that piece is still playing.
+    pub fn with_players(mut self, players: Vec<Piece>) -> Self {
+        assert!(!players.is_empty(), "a game needs at least one player");
+        assert!(
+            players
+                .iter()
+                .enumerate()
+                .all(|(i, piece)| !players[..i].contains(piece)),
+            "each piece can only be played by one player"
+        );
+        if self.is_fresh() || !players.contains(&self.state.turn) {
+            self.state.turn = players[0];
+        }
+        self.players = players;
+        self
+    }
+
+    pub fn players(&self) -> &[Piece] {
+        &self.players
+    }
+
+    /// Picks the first player of every game at random, starting with this
+    /// one unless it is already under way. Passing a seed makes the sequence
+    /// of first players reproducible.
+    #[cfg(feature = "rand")]
+    pub fn with_random_start(mut self, seed: Option<u64>) -> Self {
+        let mut rng = seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+        if self.is_fresh() {
+            self.state.turn = random_piece(&mut rng, &self.players);
+        }
+        self.first_player_rng = Some(rng);
+        self
+    }
+
+    /// Requires `win_length` pieces in a row to win instead of a whole row,
+    /// column or diagonal. The length is clamped to the board's longer side,
+    /// and to at least one even on a board with no cells.
+    pub fn with_win_length(mut self, win_length: usize) -> Self {
+        let (width, height) = (self.state.board.width(), self.state.board.height());
+        self.win_length = win_length.clamp(1, width.max(height).max(1));
+        self.winning_positions = winning_positions(width, height, self.win_length);
+        self
+    }
+
+    pub fn win_length(&self) -> usize {
+        self.win_length
+    }
+
+    pub fn winning_positions(&self) -> &[Vec<Position>] {
+        &self.winning_positions
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let save = SaveFile {
+            state: self.state.clone(),
+            win_length: Some(self.win_length),
+            players: Some(self.players.clone()),
+        };
+        let json = serde_json::to_string_pretty(&save)?;
+        fs::write(path, json)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn load(path: &Path) -> Result<Game, TicTacToeError> {
+        let json = fs::read_to_string(path)?;
+        let save: SaveFile = serde_json::from_str(&json)
+            .map_err(|e| TicTacToeError::InvalidSaveFile(e.to_string()))?;
+        let mut state = save.state;
+        if !state.board.is_consistent() {
+            return Err(TicTacToeError::InvalidSaveFile(
+                "board width does not match its positions".to_string(),
+            ));
+        }
+        let (width, height) = (state.board.width(), state.board.height());
+        let mut game = Game::on_board(Board::with_dimensions(width, height));
+        if let Some(win_length) = save.win_length {
+            if win_length == 0 || win_length > width.max(height) {
+                return Err(TicTacToeError::InvalidSaveFile(format!(
+                    "a win length of {} doesn't fit a {}x{} board",
+                    win_length, width, height
+                )));
+            }
+            game = game.with_win_length(win_length);
+        }
+        if let Some(players) = save.players {
+            let repeated = players
+                .iter()
+                .enumerate()
+                .any(|(i, piece)| players[..i].contains(piece));
+            if players.is_empty() || repeated || !players.contains(&state.turn) {
+                return Err(TicTacToeError::InvalidSaveFile(
+                    "the players don't make a valid turn order".to_string(),
+                ));
+            }
+            game = game.with_players(players);
+        }
+        state
+            .board
+            .validate(&game.players, game.starter_of(&state), game.win_length)?;
+        game.state = state;
+        Ok(game)
+    }
+
+    /// Works out who opened the game `state` came from by counting its moves
+    /// back from the player whose turn it is. A finished game leaves the turn
+    /// with whoever played last, so that move isn't counted.
+    #[cfg(feature = "serde")]
+    fn starter_of(&self, state: &GameState) -> Piece {
+        let board = &state.board;
+        let mut moves = board.move_count();
+        let finished = board.winner(self.win_length).is_some()
+            || board.is_full()
+            || board.is_dead(self.win_length);
+        if finished && moves > 0 {
+            moves -= 1;
+        }
+        self.players
+            .iter()
+            .copied()
+            .find(|&first| {
+                (0..moves).fold(first, |piece, _| next_player(&self.players, piece)) == state.turn
+            })
+            .unwrap_or(state.turn)
+    }
+
+    /// Describes the game as a JSON object with the `board` as rows of piece
+    /// symbols or nulls, whose `turn` it is, and its `status`: "in-progress",
+    /// "draw" or the winner's symbol.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        let board = (0..self.state.board.height())
+            .map(|y| {
+                (0..self.state.board.width())
+                    .map(|x| {
+                        self.state
+                            .board
+                            .get_position((x, y))
+                            .ok()
+                            .flatten()
+                            .map(|piece| piece.to_string())
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let status = match self.status() {
+            GameStatus::InProgress => "in-progress".to_string(),
+            GameStatus::Draw => "draw".to_string(),
+            GameStatus::Win(winner) => winner.to_string(),
+        };
+        serde_json::json!({
+            "board": board,
+            "turn": self.state.turn.to_string(),
+            "status": status,
+        })
+        .to_string()
+    }
+
+    fn reset_state(&mut self) -> GameState {
+        GameState {
+            board: Board::with_dimensions(self.state.board.width(), self.state.board.height()),
+            turn: self.next_first_turn(),
+            move_number: 0,
+        }
+    }
+
+    fn next_first_turn(&mut self) -> Piece {
+        #[cfg(feature = "rand")]
+        {
+            if let Some(rng) = self.first_player_rng.as_mut() {
+                return random_piece(rng, &self.players);
+            }
+        }
+        self.change_turn()
+    }
+
+    fn display_board(&mut self) {
+        match self.winning_line() {
+            Some((_, line)) if self.analysis => self.print_board(&line),
+            _ => self.print_board(&[]),
+        }
+    }
+
+    /// Whether moves can still be played after a win, in analysis mode.
+    fn is_analysing_win(&self) -> bool {
+        self.analysis && matches!(self.status(), GameStatus::Win(_)) && !self.state.board.is_full()
+    }
+
+    fn print_board(&mut self, highlight: &[Position]) {
+        #[cfg(feature = "serde")]
+        {
+            if self.output_format == OutputFormat::Json {
+                let json = self.to_json();
+                let _ = writeln!(self.output, "{}", json);
+                return;
+            }
+        }
+        let on_terminal = matches!(self.output, Output::Stdout(_)) && io::stdout().is_terminal();
+        if self.redraw && on_terminal {
+            // Clear the screen and move the cursor back to the top-left.
+            let _ = write!(self.output, "\x1b[2J\x1b[H");
+        }
+        #[cfg(feature = "color")]
+        {
+            if on_terminal {
+                let first = if self.one_based { 1 } else { 0 };
+                let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+                let rendered = self.state.board.render_colored_with(
+                    &mut stdout,
+                    first,
+                    highlight,
+                    &self.symbols,
+                );
+                if rendered.is_ok() {
+                    say!(self);
+                    return;
+                }
+            }
+        }
+        if highlight.is_empty() {
+            say!(self, "{}", self.state.board.render_grid_with(&self.symbols));
+        } else {
+            say!(
+                self,
+                "{}",
+                self.state
+                    .board
+                    .render_highlighted_with(highlight, &self.symbols)
+            );
+        }
+    }
+
+    // In JSON mode the output carries nothing but the board, one object a
+    // line, so prompts and announcements go to stderr instead.
+    fn messages(&mut self) -> Box<dyn Write + '_> {
+        #[cfg(feature = "serde")]
+        {
+            if self.output_format == OutputFormat::Json {
+                return Box::new(io::stderr());
+            }
+        }
+        Box::new(&mut self.output)
+    }
+
+    fn change_turn(&self) -> Piece {
+        let next = self.player_after(self.state.turn);
+        trace!("turn passes from {} to {}", self.state.turn, next);
+        next
+    }
+
+    fn player_after(&self, piece: Piece) -> Piece {
+        next_player(&self.players, piece)
+    }
+
+    pub fn current_turn(&self) -> Piece {
+        self.state.turn
+    }
+
+    /// Whether nothing has been played yet: the board is empty and no move
+    /// has been made.
+    pub fn is_fresh(&self) -> bool {
+        self.state.board.is_empty() && self.state.move_number == 0
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.state.board
+    }
+
+    /// Places the current player's piece at (x, y), records a win or draw if
+    /// the move ends the game, and otherwise passes the turn on.
+    pub fn apply_move(&mut self, x: usize, y: usize) -> Result<(), TicTacToeError> {
+        let analysing = self.is_analysing_win();
+        if self.status() != GameStatus::InProgress && !analysing {
+            return Err(TicTacToeError::GameOver);
+        }
+        let state = self.state.place(x, y, &self.players)?;
+        let piece = self.state.turn;
+        self.history.push(std::mem::replace(&mut self.state, state));
+        self.moves.push((piece, x, y));
+        self.undone.clear();
+        debug!(
+            "move {}: {} plays {} {}",
+            self.state.move_number, piece, x, y
+        );
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_move(piece, x, y);
+        }
+        // The game was already decided, so just carry on in player order.
+        if analysing {
+            return Ok(());
+        }
+        match self.status() {
+            GameStatus::Win(winner) => {
+                info!("{} wins after {} moves", winner, self.state.move_number);
+                self.first_win = self.winning_line();
+                self.record_win(winner);
+                if let Some(observer) = self.observer.as_mut() {
+                    observer.on_win(winner);
+                }
+                // Analysis carries on in player order. Otherwise the game is
+                // over, and the last mover keeps the turn so the next game's
+                // first player follows on from them.
+                if !self.analysis {
+                    self.state.turn = piece;
+                }
+            }
+            GameStatus::Draw => {
+                info!("the game is drawn after {} moves", self.state.move_number);
+                self.draws += 1;
+                if let Some(observer) = self.observer.as_mut() {
+                    observer.on_draw();
+                }
+                self.state.turn = piece;
+            }
+            GameStatus::InProgress => {
+                trace!("turn passes from {} to {}", piece, self.state.turn)
+            }
+        }
+        Ok(())
+    }
+
+    /// Clears the board for the next game, keeping the scores.
+    pub fn start_new_game(&mut self) {
+        self.state = self.reset_state();
+        self.history.clear();
+        self.moves.clear();
+        self.undone.clear();
+        self.first_win = None;
+        self.swapped = false;
+        self.batch.clear();
+    }
+
+    /// Whether the player to move may swap sides under the pie rule.
+    pub fn can_swap(&self) -> bool {
+        self.pie_rule && !self.swapped && self.players.len() == 2 && self.moves.len() == 1
+    }
+
+    /// Swaps who controls the two pieces under the pie rule, so the player
+    /// to move takes over the opening move and the opener plays on with the
+    /// other piece. The board and whose turn it is stay as they were.
+    pub fn swap_sides(&mut self) -> Result<(), TicTacToeError> {
+        if !self.can_swap() {
+            return Err(TicTacToeError::SwapNotAllowed);
+        }
+        let (first, second) = (self.players[0], self.players[1]);
+        let (was_first, was_second) = (self.player(first), self.player(second));
+        // An AI remembers its own piece, so it has to be rebuilt for the new one.
+        let take = |player: Player, piece: Piece| match player {
+            Player::Ai(ai) => Player::Ai(AiPlayer::new(piece, ai.difficulty())),
+            Player::Human => Player::Human,
+        };
+        self.controllers.insert(first, take(was_second, first));
+        self.controllers.insert(second, take(was_first, second));
+        self.swapped = true;
+        Ok(())
+    }
+
+    /// Takes back the last move. Taking back the move that ended the game
+    /// also takes its result off the scoreboard, so redoing it doesn't
+    /// count it twice.
+    pub fn undo(&mut self) -> Result<(), TicTacToeError> {
+        let finished = self.status();
+        self.state = self.history.pop().ok_or(TicTacToeError::NothingToUndo)?;
+        self.undone.extend(self.moves.pop());
+        if self.status() == GameStatus::InProgress {
+            match finished {
+                GameStatus::Win(winner) => {
+                    let wins = match winner {
+                        Piece::Naught => &mut self.wins_naught,
+                        Piece::Cross => &mut self.wins_cross,
+                        Piece::Triangle => &mut self.wins_triangle,
+                    };
+                    *wins = wins.saturating_sub(1);
+                }
+                GameStatus::Draw => self.draws = self.draws.saturating_sub(1),
+                GameStatus::InProgress => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Plays the most recently undone move again. Playing any other move
+    /// forgets the undone ones.
+    pub fn redo(&mut self) -> Result<(), TicTacToeError> {
+        let (_piece, x, y) = self.undone.pop().ok_or(TicTacToeError::NothingToRedo)?;
+        let undone = std::mem::take(&mut self.undone);
+        self.apply_move(x, y)?;
+        self.undone = undone;
+        Ok(())
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// Lists the moves played so far, one "piece x y" line each.
+    pub fn move_list(&self) -> String {
+        self.moves
+            .iter()
+            .map(|(piece, x, y)| format!("{} {} {}\n", piece, x, y))
+            .collect()
+    }
+
+    /// Records the game as a header of its board size and win length, then
+    /// the players in turn order, followed by each move as piece:x,y, e.g.
+    /// "3x3k3 OX O:1,1 X:0,0".
+    pub fn to_notation(&self) -> String {
+        let mut notation = format!(
+            "{}x{}k{} ",
+            self.state.board.width(),
+            self.state.board.height(),
+            self.win_length
+        );
+        for piece in &self.players {
+            notation.push_str(&piece.to_string());
+        }
+        for (piece, x, y) in &self.moves {
+            notation.push_str(&format!(" {}:{},{}", piece, x, y));
+        }
+        notation
+    }
+
+    /// Plays back a game written by `to_notation`. The header may be left
+    /// out for a default-sized game, the win length for one played across
+    /// the board's shorter side, and the players for Naught and Cross.
+    pub fn from_notation(notation: &str) -> Result<Game, TicTacToeError> {
+        let mut tokens = notation.split_whitespace().peekable();
+        let mut game = match tokens.peek().and_then(|token| token.split_once('x')) {
+            Some((width, rest)) => {
+                let token = tokens.next().unwrap_or_default();
+                let invalid = || TicTacToeError::InvalidNotation(token.to_string());
+                let number = |number: &str| number.parse::<usize>().ok().filter(|&n| n > 0);
+                let (height, win_length) = match rest.split_once('k') {
+                    Some((height, win_length)) => {
+                        (height, Some(number(win_length).ok_or_else(invalid)?))
+                    }
+                    None => (rest, None),
+                };
+                let (width, height) = match (number(width), number(height)) {
+                    (Some(width), Some(height)) => (width, height),
+                    _ => return Err(invalid()),
+                };
+                let game = Game::new_rectangular(width, height);
+                match win_length {
+                    Some(win_length) if win_length > width.max(height) => return Err(invalid()),
+                    Some(win_length) => game.with_win_length(win_length),
+                    None => game,
+                }
+            }
+            None => Game::new(None),
+        };
+        if let Some(token) = tokens.next_if(|token| !token.contains(':')) {
+            let invalid = || TicTacToeError::InvalidNotation(token.to_string());
+            let players = token
+                .chars()
+                .map(|symbol| parse_piece(&symbol.to_string()))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(invalid)?;
+            let repeated = players
+                .iter()
+                .enumerate()
+                .any(|(i, piece)| players[..i].contains(piece));
+            if repeated {
+                return Err(invalid());
+            }
+            game = game.with_players(players);
+        }
+        for token in tokens {
+            let invalid = || TicTacToeError::InvalidNotation(token.to_string());
+            let (piece, coords) = token.split_once(':').ok_or_else(invalid)?;
+            let piece = parse_piece(piece).ok_or_else(invalid)?;
+            let (x, y) = parse_move(coords).map_err(|_| invalid())?;
+            game.replay_moves(&[(piece, x, y)])?;
+        }
+        Ok(game)
+    }
+
+    /// Rebuilds a game by playing `moves` in order on a board `size` wide, or
+    /// the default size for `None`. The players, and the order they move in,
+    /// are taken from the moves, so three-player games replay too.
+    pub fn replay(moves: &[Move], size: Option<usize>) -> Result<Game, TicTacToeError> {
+        let players = players_of(moves.iter().map(|&(piece, _, _)| piece));
+        let mut game = Game::new(size).with_players(players);
+        game.replay_moves(moves)?;
+        Ok(game)
+    }
+
+    /// Plays `moves` in order, stopping at the first one that is illegal. On a
+    /// fresh game the first move decides who starts.
+    pub fn replay_moves(&mut self, moves: &[Move]) -> Result<(), TicTacToeError> {
+        if let (true, Some(&(piece, _, _))) = (self.moves.is_empty(), moves.first()) {
+            self.state.turn = piece;
+        }
+        for &(piece, x, y) in moves {
+            if piece != self.state.turn {
+                return Err(TicTacToeError::OutOfTurn(piece));
+            }
+            self.apply_move(x, y)?;
+        }
+        Ok(())
+    }
+
+    /// Plays a game on a board `size` wide, or the default size for `None`,
+    /// from a file with one move per line, either "x y" or the "P x y"
+    /// format written by `move_list`. The players are taken from the
+    /// pieces the file names. Blank lines are skipped and a malformed line
+    /// is reported by its line number.
+    pub fn from_move_file(path: &Path, size: Option<usize>) -> Result<Game, TicTacToeError> {
+        let contents = fs::read_to_string(path)?;
+        let records = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                parse_move_record(line).map_err(|_| TicTacToeError::InvalidMoveFile(index + 1))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let players = players_of(records.iter().filter_map(|&(piece, _, _)| piece));
+        let mut game = Game::new(size).with_players(players);
+        for (piece, x, y) in records {
+            match piece {
+                Some(piece) => game.replay_moves(&[(piece, x, y)])?,
+                None => game.apply_move(x, y)?,
+            }
+        }
+        Ok(game)
+    }
+
+    /// Reads the next command from stdin. End of input (Ctrl-D, or a closed
+    /// pipe) reads as `Command::Quit` so `run` stops instead of re-prompting
+    /// forever, while a line that can't be parsed is an `InvalidMoveInput`
+    /// error worth re-prompting for. Any other read failure is returned as
+    /// `TicTacToeError::Io`. With a move timeout, running out of time is a
+    /// `TicTacToeError::TimedOut`. A line of several coordinate pairs is
+    /// played one pair per call, before reading any more input.
+    fn get_move(&mut self) -> Result<Command, TicTacToeError> {
+        if let Some((pair, x, y)) = self.batch.pop_front() {
+            self.batch_pair = Some(pair);
+            return Ok(Command::Place(x, y));
+        }
+        self.batch_pair = None;
+        let read = match self.turn_deadline {
+            None => {
+                let mut line = String::new();
+                io::stdin().read_line(&mut line).map(|_| line)
+            }
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                match self.stdin_lines().recv_timeout(remaining) {
+                    Ok(read) => read,
+                    Err(RecvTimeoutError::Timeout) => return Err(TicTacToeError::TimedOut),
+                    Err(RecvTimeoutError::Disconnected) => Ok(String::new()),
+                }
+            }
+        };
+        match read {
+            Ok(line) if line.is_empty() => Ok(Command::Quit),
+            Ok(line) => self.parse_line(&line),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                Err(TicTacToeError::InvalidMoveInput)
+            }
+            Err(e) => Err(TicTacToeError::Io(e)),
+        }
+    }
+
+    // A blocking read_line can't be abandoned when the clock runs out, so
+    // timed games read stdin on a background thread that forwards each line
+    // over a channel, and get_move waits on the channel with a timeout
+    // instead. The thread lives as long as stdin stays open, and a line typed
+    // after the timeout is kept for the next player's read. A line that isn't
+    // UTF-8 is passed on as an error to re-prompt for, without ending input.
+    fn stdin_lines(&mut self) -> &Receiver<io::Result<String>> {
+        self.stdin_lines.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || loop {
+                let mut line = String::new();
+                let read = io::stdin().read_line(&mut line);
+                let finished = match &read {
+                    Ok(n) => *n == 0,
+                    Err(e) => e.kind() != io::ErrorKind::InvalidData,
+                };
+                if sender.send(read.map(|_| line)).is_err() || finished {
+                    break;
+                }
+            });
+            receiver
+        })
+    }
+
+    // Reads a line that isn't a move, such as the answer to a draw offer. In
+    // a timed game the background thread owns stdin, so the line comes from it.
+    fn read_answer(&mut self) -> io::Result<String> {
+        match &self.stdin_lines {
+            Some(lines) => lines.recv().unwrap_or_else(|_| Ok(String::new())),
+            None => {
+                let mut line = String::new();
+                io::stdin().read_line(&mut line).map(|_| line)
+            }
+        }
+    }
+
+    // Asks each of the other players in turn whether they agree to a draw
+    // offered by the player to move, stopping at the first refusal. The
+    // computer always plays on, and so does anyone whose input has ended.
+    fn offer_draw(&mut self) -> bool {
+        let offered_by = self.state.turn;
+        let mut piece = self.change_turn();
+        while piece != offered_by {
+            if let Player::Ai(_) = self.player(piece) {
+                say!(self, "The computer declines the draw.");
+                return false;
+            }
+            say!(
+                self,
+                "{} offers a draw. {}, do you accept? (y/n)",
+                self.symbols.symbol(offered_by),
+                self.symbols.symbol(piece)
+            );
+            loop {
+                match self.read_answer() {
+                    Ok(answer) => match answer.trim().to_lowercase().as_str() {
+                        "y" | "yes" => break,
+                        "" | "n" | "no" => {
+                            say!(self, "{} declines the draw.", self.symbols.symbol(piece));
+                            return false;
+                        }
+                        _ => say!(self, "Please answer y or n:"),
+                    },
+                    Err(_) => return false,
+                }
+            }
+            piece = self.player_after(piece);
+        }
+        true
+    }
+
+    // Queues up a line of more than one "x y" pair, such as "0 0 1 1 2 2",
+    // and returns the first of them. Anything else is a single command.
+    fn parse_line(&mut self, line: &str) -> Result<Command, TicTacToeError> {
+        let tokens = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .collect::<Vec<_>>();
+        if tokens.len() <= 2 {
+            return self.parse_command(line);
+        }
+        if tokens.len() % 2 == 1 {
+            return Err(TicTacToeError::InvalidMoveInput);
+        }
+        let moves = tokens
+            .chunks(2)
+            .map(|pair| parse_move(&pair.join(" ")).map(|(x, y)| self.internal_coords(x, y)))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.batch = moves
+            .into_iter()
+            .enumerate()
+            .map(|(i, (x, y))| (i + 1, x, y))
+            .collect();
+        self.get_move()
+    }
+
+    fn parse_command(&self, input: &str) -> Result<Command, TicTacToeError> {
+        match input.trim() {
+            "undo" => return Ok(Command::Undo),
+            "redo" => return Ok(Command::Redo),
+            "hint" => return Ok(Command::Hint),
+            "status" => return Ok(Command::Status),
+            "swap" => return Ok(Command::Swap),
+            "draw" => return Ok(Command::OfferDraw),
+            "resign" | "forfeit" => return Ok(Command::Resign),
+            "quit" | "q" => return Ok(Command::Quit),
+            _ => {}
+        }
+        if self.cell_numbers && input.split_whitespace().count() == 1 {
+            let (x, y) =
+                parse_cell_number(input, self.state.board.width(), self.state.board.height())?;
+            return Ok(Command::Place(x, y));
+        }
+        let (x, y) = parse_move(input)?;
+        let (x, y) = self.internal_coords(x, y);
+        Ok(Command::Place(x, y))
+    }
+
+    // A 1-based 0 wraps around to usize::MAX, which set_piece rejects as out
+    // of bounds, and display_coords wraps it back when reporting it.
+    fn internal_coords(&self, x: usize, y: usize) -> (usize, usize) {
+        if self.one_based {
+            (x.wrapping_sub(1), y.wrapping_sub(1))
+        } else {
+            (x, y)
+        }
+    }
+
+    fn display_coords(&self, x: usize, y: usize) -> (usize, usize) {
+        if self.one_based {
+            (x.wrapping_add(1), y.wrapping_add(1))
+        } else {
+            (x, y)
+        }
+    }
+
+    fn display_error(&self, e: TicTacToeError) -> TicTacToeError {
+        match e {
+            TicTacToeError::OutOfBounds(x, y) => {
+                let (x, y) = self.display_coords(x, y);
+                TicTacToeError::OutOfBounds(x, y)
+            }
+            TicTacToeError::PieceInPosition(x, y) => {
+                let (x, y) = self.display_coords(x, y);
+                TicTacToeError::PieceInPosition(x, y)
+            }
+            e => e,
+        }
+    }
+
+    // Says what was wrong with a move the player typed, in the coordinates
+    // they use, and what to type instead.
+    fn explain_move_error(&self, e: TicTacToeError) -> (String, String) {
+        let first = self.display_coords(0, 0).0;
+        match self.display_error(e) {
+            e @ TicTacToeError::PieceInPosition(..) => (
+                format!("That cell is taken: {}", e),
+                "Pick an empty one:".to_string(),
+            ),
+            e @ TicTacToeError::OutOfBounds(..) => (
+                format!("That's off the board: {}", e),
+                format!(
+                    "Columns go from {} to {} and rows from {} to {}:",
+                    first,
+                    first + self.state.board.width() - 1,
+                    first,
+                    first + self.state.board.height() - 1
+                ),
+            ),
+            TicTacToeError::InvalidMoveInput if self.cell_numbers => (
+                "Couldn't read that move".to_string(),
+                format!(
+                    "Type a cell number from 1 to {}, a column and a row, or a command like hint:",
+                    self.state.board.cell_count()
+                ),
+            ),
+            TicTacToeError::InvalidMoveInput => (
+                "Couldn't read that move".to_string(),
+                format!(
+                    "Type a column and a row, like \"{} {}\", or a command like hint:",
+                    first, first
+                ),
+            ),
+            e => (format!("Invalid move: {}", e), "Try again:".to_string()),
+        }
+    }
+
+    /// Suggests a move for the current player using the minimax AI.
+    pub fn hint(&self) -> (usize, usize) {
+        AiPlayer::new(self.state.turn, Difficulty::Perfect)
+            .with_reverse(self.reverse)
+            .best_move(&self.state.board, &self.winning_positions)
+    }
+
+    /// Every legal move for the current player with its minimax score, best
+    /// first. Equal scores keep row-major order.
+    pub fn ranked_moves(&self) -> Vec<((usize, usize), i32)> {
+        if self.status() != GameStatus::InProgress {
+            return Vec::new();
+        }
+        let mut moves = AiPlayer::new(self.state.turn, Difficulty::Perfect)
+            .with_reverse(self.reverse)
+            .score_moves(&self.state.board, &self.winning_positions);
+        moves.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        moves
+    }
+
+    fn is_ai_turn(&self) -> bool {
+        matches!(self.player(self.state.turn), Player::Ai(_))
+    }
+
+    fn has_human_player(&self) -> bool {
+        self.players
+            .iter()
+            .any(|&piece| matches!(self.player(piece), Player::Human))
+    }
+
+    fn check_slice_for_winner(&self, slice: &[Position]) -> Option<Piece> {
+        self.state.board.line_owner(slice)
+    }
+
+    pub fn check_winner(&self) -> Option<Piece> {
+        self.winning_line().map(|(winner, _)| winner)
+    }
+
+    /// The line that won the game and who completed it. In analysis mode
+    /// this stays the first line completed, whatever is played afterwards,
+    /// until that line is undone.
+    pub fn winning_line(&self) -> Option<(Piece, Vec<Position>)> {
+        if let Some((owner, line)) = &self.first_win {
+            if self.check_slice_for_winner(line) == Some(*owner) {
+                return Some((*owner, line.clone()));
+            }
+        }
+        for slice in self.winning_positions.iter() {
+            if let Some(winner) = self.check_slice_for_winner(slice) {
+                return Some((winner, slice.clone()));
+            }
+        }
+        None
+    }
+
+    pub fn check_draw(&self) -> bool {
+        self.status() == GameStatus::Draw
+    }
+
+    /// Once no line can be completed the game is drawn, even if cells are
+    /// still empty.
+    pub fn status(&self) -> GameStatus {
+        match self.check_winner() {
+            Some(owner) if self.reverse => GameStatus::Win(self.player_after(owner)),
+            Some(winner) => GameStatus::Win(winner),
+            None if self.state.board.is_full() || self.state.board.is_dead(self.win_length) => {
+                GameStatus::Draw
+            }
+            None => GameStatus::InProgress,
+        }
+    }
+
+    /// Ends the game with its outcome, judged the same way as `status`. Where
+    /// `status` reports an unfinished game as `InProgress`, this treats it as
+    /// an error, so code that expects a finished game can use `?`.
+    pub fn into_result(self) -> Result<GameOutcome, GameInProgress> {
+        match self.status() {
+            GameStatus::Win(winner) => Ok(GameOutcome::Win(winner)),
+            GameStatus::Draw => Ok(GameOutcome::Draw),
+            GameStatus::InProgress => Err(GameInProgress(Box::new(self))),
+        }
+    }
+
+    pub fn wins(&self, piece: Piece) -> u32 {
+        match piece {
+            Piece::Naught => self.wins_naught,
+            Piece::Cross => self.wins_cross,
+            Piece::Triangle => self.wins_triangle,
+        }
+    }
+
+    pub fn draws(&self) -> u32 {
+        self.draws
+    }
+
+    /// Sums up the games won by each player and drawn, followed by how many
+    /// lines each player has completed on the current board.
+    pub fn scoreboard(&self) -> String {
+        let mut scoreboard = String::new();
+        for piece in [Piece::Cross, Piece::Naught, Piece::Triangle] {
+            if self.players.contains(&piece) {
+                scoreboard.push_str(&format!(
+                    "{}: {}  ",
+                    self.symbols.symbol(piece),
+                    self.wins(piece)
+                ));
+            }
+        }
+        let lines = [Piece::Cross, Piece::Naught, Piece::Triangle]
+            .iter()
+            .filter(|piece| self.players.contains(piece))
+            .map(|&piece| {
+                format!(
+                    "{} {}",
+                    self.symbols.symbol(piece),
+                    self.state.board.completed_lines(piece, self.win_length)
+                )
+            })
+            .collect::<Vec<_>>();
+        scoreboard.push_str(&format!(
+            "Draws: {}  Lines: {}",
+            self.draws,
+            lines.join("  ")
+        ));
+        scoreboard
+    }
+
+    /// A one-line summary of the current game: the move number, whose turn
+    /// it is, how many pieces each player has down and the game status.
+    pub fn summary(&self) -> String {
+        let counts = self.state.board.count_pieces();
+        let pieces = self
+            .players
+            .iter()
+            .map(|&piece| format!("{} {}", self.symbols.symbol(piece), counts[&piece]))
+            .collect::<Vec<_>>()
+            .join("  ");
+        let status = match self.status() {
+            GameStatus::InProgress => "in progress".to_string(),
+            GameStatus::Draw => "drawn".to_string(),
+            GameStatus::Win(winner) => format!("won by {}", self.symbols.symbol(winner)),
+        };
+        format!(
+            "Move {}, {} to play. Pieces: {}. The game is {}.",
+            self.state.move_number + 1,
+            self.symbols.symbol(self.state.turn),
+            pieces,
+            status
+        )
+    }
+
+    fn record_win(&mut self, winner: Piece) {
+        match winner {
+            Piece::Naught => self.wins_naught += 1,
+            Piece::Cross => self.wins_cross += 1,
+            Piece::Triangle => self.wins_triangle += 1,
+        }
+    }
+
+    /// Plays `moves` in order without touching stdin, stopping at the first
+    /// win or draw. Running out of moves, or hitting an illegal one under
+    /// `IllegalMovePolicy::Abort`, ends the game as `Aborted`.
+    pub fn run_with<I: Iterator<Item = (usize, usize)>>(mut self, moves: I) -> GameResult {
+        for (x, y) in moves {
+            if self.apply_move(x, y).is_err()
+                && self.illegal_move_policy == IllegalMovePolicy::Abort
+            {
+                return GameResult::Aborted;
+            }
+            match self.status() {
+                GameStatus::Win(winner) => return GameResult::Win(winner),
+                GameStatus::Draw => return GameResult::Draw,
+                GameStatus::InProgress => {}
+            }
+        }
+        GameResult::Aborted
+    }
+
+    pub fn run(mut self) {
+        say!(self, "Starting the game!");
+        // A loaded game may already be over, and there'd be nothing to play.
+        if self.status() != GameStatus::InProgress && !self.is_analysing_win() {
+            say!(self, "That game is already over, so starting a new one.");
+            self.start_new_game();
+        }
+        let mut games = 0;
+        loop {
+            'turn: loop {
+                let decided = self.status() != GameStatus::InProgress;
+                self.display_board();
+                match self.player(self.state.turn) {
+                    Player::Ai(ai) => {
+                        if !self.has_human_player() {
+                            thread::sleep(self.ai_delay);
+                        }
+                        let (x, y) = ai
+                            .with_reverse(self.reverse)
+                            .best_move(&self.state.board, &self.winning_positions);
+                        let (display_x, display_y) = self.display_coords(x, y);
+                        say!(self, "The computer plays {} {}", display_x, display_y);
+                        if let Err(e) = self.apply_move(x, y) {
+                            say!(self, "The computer's move was rejected: {}", e);
+                            return;
+                        }
+                    }
+                    Player::Human => {
+                        say!(
+                            self,
+                            "Move {}. Pick a position:",
+                            self.state.move_number + 1
+                        );
+                        if self.can_swap() {
+                            say!(
+                                self,
+                                "Or type swap to take over {}'s opening move.",
+                                self.symbols.symbol(self.moves[0].0)
+                            );
+                        }
+                        if let Some(timeout) = self.move_timeout {
+                            say!(self, "You have {:.1} seconds.", timeout.as_secs_f64());
+                        }
+                        self.turn_deadline =
+                            self.move_timeout.map(|timeout| Instant::now() + timeout);
+                        let mut offered_draw = false;
+                        loop {
+                            match self.get_move() {
+                                Ok(Command::Place(x, y)) => match self.apply_move(x, y) {
+                                    Ok(()) => break,
+                                    Err(e) => match self.batch_pair.take() {
+                                        Some(pair) => {
+                                            self.batch.clear();
+                                            let (problem, advice) = self.explain_move_error(e);
+                                            say!(
+                                                self,
+                                                "{} (pair {} of the line). The rest of the line was skipped. {}",
+                                                problem,
+                                                pair,
+                                                advice
+                                            );
+                                        }
+                                        None => {
+                                            let (problem, advice) = self.explain_move_error(e);
+                                            say!(self, "{}. {}", problem, advice);
+                                        }
+                                    },
+                                },
+                                Ok(Command::Undo) => match self.undo() {
+                                    Ok(()) => {
+                                        // Take back the computer's reply as well, so the
+                                        // human gets to replay their own move.
+                                        if self.is_ai_turn() {
+                                            self.undo().ok();
+                                        }
+                                        continue 'turn;
+                                    }
+                                    Err(e) => say!(self, "Can't undo: {}. Pick a position:", e),
+                                },
+                                Ok(Command::Redo) => match self.redo() {
+                                    // Fall through to the status check, since the redone
+                                    // move may have ended the game.
+                                    Ok(()) => break,
+                                    Err(e) => say!(self, "Can't redo: {}. Pick a position:", e),
+                                },
+                                Ok(Command::Hint) => {
+                                    let (x, y) = self.hint();
+                                    let (x, y) = self.display_coords(x, y);
+                                    say!(self, "Hint: try {} {}. Pick a position:", x, y);
+                                }
+                                Ok(Command::Swap) => match self.swap_sides() {
+                                    Ok(()) => {
+                                        say!(
+                                            self,
+                                            "Sides swapped: you take over {}, and {} moves next.",
+                                            self.symbols.symbol(self.moves[0].0),
+                                            self.symbols.symbol(self.state.turn)
+                                        );
+                                        continue 'turn;
+                                    }
+                                    Err(e) => say!(self, "Can't swap: {}. Pick a position:", e),
+                                },
+                                Ok(Command::Status) => {
+                                    say!(self, "{}", self.summary());
+                                    say!(self, "Pick a position:");
+                                }
+                                Ok(Command::OfferDraw) if offered_draw => say!(
+                                    self,
+                                    "You can only offer one draw a turn. Pick a position:"
+                                ),
+                                Ok(Command::OfferDraw) => {
+                                    offered_draw = true;
+                                    if self.offer_draw() {
+                                        info!(
+                                            "the game is drawn by agreement after {} moves",
+                                            self.state.move_number
+                                        );
+                                        self.draws += 1;
+                                        if let Some(observer) = self.observer.as_mut() {
+                                            observer.on_draw();
+                                        }
+                                        say!(self, "Draw agreed.");
+                                        break 'turn;
+                                    }
+                                    say!(
+                                        self,
+                                        "{} is still to move. Pick a position:",
+                                        self.symbols.symbol(self.state.turn)
+                                    );
+                                }
+                                Ok(Command::Resign) => {
+                                    // The player to move resigns, handing the game to
+                                    // whoever is next.
+                                    let winner = self.change_turn();
+                                    self.record_win(winner);
+                                    if let Some(observer) = self.observer.as_mut() {
+                                        observer.on_win(winner);
+                                    }
+                                    say!(
+                                        self,
+                                        "{} wins by resignation",
+                                        self.symbols.symbol(winner)
+                                    );
+                                    break 'turn;
+                                }
+                                Ok(Command::Quit) => {
+                                    say!(self, "Thanks for playing!");
+                                    return;
+                                }
+                                Err(TicTacToeError::TimedOut) => {
+                                    say!(self, "Time's up!");
+                                    self.state.turn = self.change_turn();
+                                    continue 'turn;
+                                }
+                                Err(TicTacToeError::Io(e)) => {
+                                    say!(self, "Could not read input: {}", e);
+                                    return;
+                                }
+                                Err(e) => {
+                                    let (problem, advice) = self.explain_move_error(e);
+                                    say!(self, "{}. {}", problem, advice);
+                                }
+                            };
+                        }
+                    }
+                }
+                match self.status() {
+                    // The result was announced when the line was completed.
+                    GameStatus::Win(_) if decided && self.is_analysing_win() => {}
+                    GameStatus::Win(_) if decided => {
+                        self.display_board();
+                        say!(self, "The board is full.");
+                        break;
+                    }
+                    GameStatus::Win(winner) => {
+                        let (owner, line) =
+                            self.winning_line().expect("a won game has a winning line");
+                        self.print_board(&line);
+                        if self.reverse {
+                            say!(
+                                self,
+                                "{} completed a line and loses, so {} won!",
+                                self.symbols.symbol(owner),
+                                self.symbols.symbol(winner)
+                            );
+                        } else {
+                            say!(self, "{} won!", self.symbols.symbol(winner));
+                        }
+                        if self.is_analysing_win() {
+                            say!(self, "Analysis mode: keep placing pieces.");
+                            continue;
+                        }
+                        break;
+                    }
+                    GameStatus::Draw => {
+                        say!(self, "It's a draw!");
+                        break;
+                    }
+                    GameStatus::InProgress => {}
+                }
+            }
+            // Computers would play each other forever, so stop after the
+            // games they were asked to play.
+            games += 1;
+            if !self.has_human_player() && games >= self.ai_games {
+                if games > 1 {
+                    say!(self, "Final tally after {} games:", games);
+                }
+                say!(self, "{}", self.scoreboard());
+                return;
+            }
+            say!(self, "Starting a new game!");
+            say!(self, "{}", self.scoreboard());
+            self.start_new_game();
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new(None)
+    }
+}
+
+/// Copies everything about the game, scores and undo history included,
+/// except the observer and any custom output, which can't be cloned and
+/// stay with the original. The copy prints to stdout and reads stdin afresh
+/// if it is run.
+impl Clone for Game {
+    fn clone(&self) -> Self {
+        Game {
+            state: self.state.clone(),
+            winning_positions: self.winning_positions.clone(),
+            win_length: self.win_length,
+            players: self.players.clone(),
+            controllers: self.controllers.clone(),
+            ai_delay: self.ai_delay,
+            ai_games: self.ai_games,
+            one_based: self.one_based,
+            cell_numbers: self.cell_numbers,
+            reverse: self.reverse,
+            analysis: self.analysis,
+            pie_rule: self.pie_rule,
+            swapped: self.swapped,
+            redraw: self.redraw,
+            symbols: self.symbols.clone(),
+            illegal_move_policy: self.illegal_move_policy,
+            output_format: self.output_format,
+            history: self.history.clone(),
+            moves: self.moves.clone(),
+            undone: self.undone.clone(),
+            first_win: self.first_win.clone(),
+            wins_naught: self.wins_naught,
+            wins_cross: self.wins_cross,
+            wins_triangle: self.wins_triangle,
+            draws: self.draws,
+            observer: None,
+            output: Output::Stdout(io::stdout()),
+            move_timeout: self.move_timeout,
+            turn_deadline: self.turn_deadline,
+            stdin_lines: None,
+            batch: self.batch.clone(),
+            batch_pair: self.batch_pair,
+            #[cfg(feature = "rand")]
+            first_player_rng: self.first_player_rng.clone(),
+        }
+    }
+}
+
+// Who moves after `piece`: the next player in order, wrapping round to the
+// first.
+fn next_player(players: &[Piece], piece: Piece) -> Piece {
+    let current = players
+        .iter()
+        .position(|&player| player == piece)
+        .unwrap_or(0);
+    players[(current + 1) % players.len()]
+}
+
+// The players of a recorded game, in the order they first moved. Naught and
+// Cross always take part, so a record of one piece, or none, still replays
+// as a two-player game.
+fn players_of(pieces: impl Iterator<Item = Piece>) -> Vec<Piece> {
+    let mut players = Vec::new();
+    for piece in pieces.chain(vec![Piece::Naught, Piece::Cross]) {
+        if !players.contains(&piece) {
+            players.push(piece);
+        }
+    }
+    players
+}
+
+/// Plays random legal moves on a `size`-wide board until the game is won or
+/// drawn. The same seed always plays out the same game.
+#[cfg(feature = "rand")]
+pub fn random_game(seed: u64, size: usize) -> Game {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut game = Game::new(Some(size));
+    while game.status() == GameStatus::InProgress {
+        let moves = game.state.board.empty_positions().collect::<Vec<_>>();
+        let (x, y) = moves[rng.gen_range(0..moves.len())];
+        game.apply_move(x, y)
+            .expect("empty cells are always legal moves");
+    }
+    game
+}
+
+#[cfg(feature = "rand")]
+fn random_piece(rng: &mut StdRng, players: &[Piece]) -> Piece {
+    players[rng.gen_range(0..players.len())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "serde")]
+    use std::process;
+
+    // A cloneable output, so a test can read back what `run` wrote.
+    #[cfg(feature = "serde")]
+    #[derive(Clone, Default)]
+    struct SharedOutput(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+    #[cfg(feature = "serde")]
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_output_is_only_json() {
+        let output = SharedOutput::default();
+        Game::new(None)
+            .with_ai(AiPlayer::new(Piece::Naught, Difficulty::Perfect))
+            .with_ai(AiPlayer::new(Piece::Cross, Difficulty::Perfect))
+            .with_ai_delay(Duration::from_millis(0))
+            .with_output_format(OutputFormat::Json)
+            .with_output(Box::new(output.clone()))
+            .run();
+        let written = String::from_utf8(output.0.borrow().clone()).unwrap();
+        assert!(!written.is_empty());
+        for line in written.lines() {
+            assert!(
+                serde_json::from_str::<serde_json::Value>(line).is_ok(),
+                "not JSON: {}",
+                line
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_has_the_board_turn_and_status() {
+        let mut game = Game::new(None);
+        game.apply_move(0, 0).unwrap();
+        game.apply_move(1, 1).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&game.to_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "board": [["O", null, null], [null, "X", null], [null, null, null]],
+                "turn": "O",
+                "status": "in-progress",
+            })
+        );
+        for &(x, y) in &[(1, 0), (2, 2), (2, 0)] {
+            game.apply_move(x, y).unwrap();
+        }
+        let json: serde_json::Value = serde_json::from_str(&game.to_json()).unwrap();
+        assert_eq!(json["status"], "O");
+    }
+
+    #[test]
+    fn analysis_keeps_the_first_winning_line() {
+        let mut game = Game::new(None).with_analysis(true);
+        // Naught completes the bottom row, then Cross completes the top row,
+        // which comes first when the lines are scanned.
+        for &(x, y) in &[(0, 2), (0, 0), (1, 2), (1, 0), (2, 2), (2, 0)] {
+            game.apply_move(x, y).unwrap();
+        }
+        assert_eq!(game.status(), GameStatus::Win(Piece::Naught));
+        let (owner, line) = game.winning_line().unwrap();
+        assert_eq!(owner, Piece::Naught);
+        assert!(line.iter().all(|position| position.y == 2));
+        assert_eq!(game.wins(Piece::Naught), 1);
+        assert_eq!(game.wins(Piece::Cross), 0);
+        assert!(matches!(
+            game.into_result(),
+            Ok(GameOutcome::Win(Piece::Naught))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn saves_keep_the_rules() {
+        let mut game = Game::new(Some(5)).with_win_length(3).with_players(vec![
+            Piece::Cross,
+            Piece::Naught,
+            Piece::Triangle,
+        ]);
+        game.apply_move(2, 2).unwrap();
+        let path = std::env::temp_dir().join(format!("tic-tac-toe-save-{}.json", process::id()));
+        game.save(&path).unwrap();
+        let loaded = Game::load(&path);
+        fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.win_length(), 3);
+        assert_eq!(
+            loaded.players(),
+            &[Piece::Cross, Piece::Naught, Piece::Triangle]
+        );
+        assert_eq!(loaded.current_turn(), Piece::Naught);
+        assert_eq!(loaded.board(), game.board());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn finished_saves_still_load() {
+        let mut game = Game::new(None);
+        for &(x, y) in &[(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            game.apply_move(x, y).unwrap();
+        }
+        assert_ne!(game.status(), GameStatus::InProgress);
+        let path = std::env::temp_dir().join(format!("tic-tac-toe-won-{}.json", process::id()));
+        game.save(&path).unwrap();
+        let loaded = Game::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().board(), game.board());
+    }
+
+    #[test]
+    fn notation_keeps_the_rules() {
+        let mut game = Game::new(Some(5)).with_win_length(3);
+        for &(x, y) in &[(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            game.apply_move(x, y).unwrap();
+        }
+        assert_eq!(game.status(), GameStatus::Win(Piece::Naught));
+        let notation = game.to_notation();
+        assert!(notation.starts_with("5x5k3 OX "));
+        let replayed = Game::from_notation(&notation).unwrap();
+        assert_eq!(replayed.status(), GameStatus::Win(Piece::Naught));
+        assert_eq!(replayed.moves(), game.moves());
+
+        let mut game = Game::new(Some(5)).with_win_length(3).with_players(vec![
+            Piece::Cross,
+            Piece::Naught,
+            Piece::Triangle,
+        ]);
+        for &(x, y) in &[(0, 0), (1, 0), (2, 0), (0, 1)] {
+            game.apply_move(x, y).unwrap();
+        }
+        let replayed = Game::from_notation(&game.to_notation()).unwrap();
+        assert_eq!(replayed.players(), game.players());
+        assert_eq!(replayed.moves(), game.moves());
+        assert_eq!(replayed.current_turn(), game.current_turn());
+    }
+
+    #[test]
+    fn notation_without_a_header_is_a_default_game() {
+        let game = Game::from_notation("O:1,1 X:0,0").unwrap();
+        assert_eq!(game.board().width(), 3);
+        assert_eq!(game.moves().len(), 2);
+        assert!(Game::from_notation("3x3k4 O:1,1").is_err());
+        assert!(Game::from_notation("3x3 OO O:1,1").is_err());
+    }
+
+    #[test]
+    fn three_player_games_replay() {
+        let mut game =
+            Game::new(Some(5)).with_players(vec![Piece::Naught, Piece::Cross, Piece::Triangle]);
+        for &(x, y) in &[(0, 0), (4, 4), (2, 2), (1, 0)] {
+            game.apply_move(x, y).unwrap();
+        }
+        let replayed = Game::replay(game.moves(), Some(5)).unwrap();
+        assert_eq!(replayed.players(), game.players());
+        assert_eq!(replayed.board(), game.board());
+        assert_eq!(replayed.current_turn(), Piece::Cross);
+
+        let path =
+            std::env::temp_dir().join(format!("tic-tac-toe-moves-{}.txt", std::process::id()));
+        fs::write(&path, game.move_list()).unwrap();
+        let loaded = Game::from_move_file(&path, Some(5));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().board(), game.board());
+    }
+
+    #[test]
+    fn completing_a_line_in_reverse_loses() {
+        let mut game = Game::new(None).with_reverse(true);
+        for &(x, y) in &[(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            game.apply_move(x, y).unwrap();
+        }
+        let (owner, _) = game.winning_line().unwrap();
+        assert_eq!(owner, Piece::Naught);
+        assert_eq!(game.status(), GameStatus::Win(Piece::Cross));
+        assert_eq!(game.wins(Piece::Cross), 1);
+        assert_eq!(game.wins(Piece::Naught), 0);
+    }
+
+    #[test]
+    fn reverse_hints_avoid_completing_a_line() {
+        let mut game = Game::new(None).with_reverse(true);
+        for &(x, y) in &[(0, 0), (2, 2), (0, 1), (1, 2)] {
+            game.apply_move(x, y).unwrap();
+        }
+        assert_ne!(game.hint(), (0, 2));
+        assert_ne!(game.ranked_moves()[0].0, (0, 2));
+    }
+
+    #[test]
+    fn four_on_a_diagonal_wins_on_5x5() {
+        let mut game = Game::new(Some(5)).with_win_length(4);
+        game.state.board = Board::with_size(5)
+            .apply_moves(&[
+                (0, 0, Piece::Cross),
+                (1, 1, Piece::Cross),
+                (2, 2, Piece::Cross),
+                (3, 3, Piece::Cross),
+                (4, 4, Piece::Naught),
+            ])
+            .unwrap();
+        assert_eq!(game.status(), GameStatus::Win(Piece::Cross));
+        let mut whole_lines = Game::new(Some(5));
+        whole_lines.state.board = game.state.board.clone();
+        assert_eq!(whole_lines.status(), GameStatus::InProgress);
+    }
+
+    #[test]
+    fn win_lengths_fit_empty_boards() {
+        let game = Game::new(Some(0)).with_win_length(3);
+        assert_eq!(game.win_length(), 1);
+        assert!(game.winning_positions().is_empty());
+    }
+
+    #[test]
+    fn tallies_carry_across_games() {
+        let top_row = [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)];
+        let drawn = [
+            (0, 0),
+            (1, 1),
+            (2, 2),
+            (0, 2),
+            (2, 0),
+            (1, 0),
+            (1, 2),
+            (2, 1),
+            (0, 1),
+        ];
+        let mut game = Game::new(None);
+        for (i, script) in [&top_row[..], &top_row[..], &drawn[..]].iter().enumerate() {
+            if i > 0 {
+                game.start_new_game();
+            }
+            for &(x, y) in script.iter() {
+                if game.status() != GameStatus::InProgress {
+                    break;
+                }
+                game.apply_move(x, y).unwrap();
+            }
+        }
+        // Naught opens the first game and Cross the second, so each wins
+        // one with the top row.
+        assert_eq!(game.wins(Piece::Naught), 1);
+        assert_eq!(game.wins(Piece::Cross), 1);
+        assert_eq!(game.draws(), 1);
+        assert!(game.scoreboard().starts_with("X: 1  O: 1  Draws: 1"));
+    }
+
+    #[test]
+    fn place_follows_the_turn_order() {
+        let players = [Piece::Naught, Piece::Cross, Piece::Triangle];
+        let state = Game::new(Some(4)).with_players(players.to_vec()).state;
+        let state = state.place(0, 0, &players).unwrap();
+        assert_eq!(state.turn, Piece::Cross);
+        let state = state.place(1, 0, &players).unwrap();
+        assert_eq!(state.turn, Piece::Triangle);
+        let state = state.place(2, 0, &players).unwrap();
+        assert_eq!(state.turn, Piece::Naught);
+    }
+
+    #[test]
+    fn redoing_a_win_counts_it_once() {
+        let mut game = Game::new(None).with_analysis(true);
+        for &(x, y) in &[(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            game.apply_move(x, y).unwrap();
+        }
+        assert_eq!(game.wins(Piece::Naught), 1);
+        game.undo().unwrap();
+        assert_eq!(game.wins(Piece::Naught), 0);
+        game.redo().unwrap();
+        assert_eq!(game.wins(Piece::Naught), 1);
+        assert_eq!(game.status(), GameStatus::Win(Piece::Naught));
+    }
+
+    #[test]
+    fn redoing_a_draw_counts_it_once() {
+        let mut game = Game::new(None);
+        // No line can be completed once a draw is certain, which happens
+        // before the board fills up.
+        for &(x, y) in &[
+            (0, 0),
+            (1, 1),
+            (2, 2),
+            (0, 2),
+            (2, 0),
+            (1, 0),
+            (1, 2),
+            (2, 1),
+            (0, 1),
+        ] {
+            if game.status() != GameStatus::InProgress {
+                break;
+            }
+            game.apply_move(x, y).unwrap();
+        }
+        assert_eq!(game.status(), GameStatus::Draw);
+        game.undo().unwrap();
+        game.redo().unwrap();
+        assert_eq!(game.draws(), 1);
+    }
+
+    #[test]
+    fn blank_lines_are_invalid_input_rather_than_quitting() {
+        let mut game = Game::new(None);
+        for line in &["", "\n", "   \n"] {
+            assert!(
+                matches!(game.parse_line(line), Err(TicTacToeError::InvalidMoveInput)),
+                "{:?} should be invalid",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn a_dead_board_is_drawn_before_it_fills() {
+        let mut game = Game::new(None);
+        for &(x, y) in &[
+            (1, 1),
+            (0, 0),
+            (1, 0),
+            (1, 2),
+            (2, 1),
+            (0, 1),
+            (0, 2),
+            (2, 0),
+        ] {
+            game.apply_move(x, y).unwrap();
+            if game.status() != GameStatus::InProgress {
+                break;
+            }
+        }
+        assert_eq!(game.status(), GameStatus::Draw);
+        assert!(game.board().is_dead(3));
+        assert!(!game.board().is_full());
+        assert_eq!(game.draws(), 1);
+    }
+
+    #[test]
+    fn a_cloned_game_moves_on_alone() {
+        let mut game = Game::new(None);
+        game.apply_move(1, 1).unwrap();
+        let mut branch = game.clone();
+        branch.apply_move(0, 0).unwrap();
+        branch.apply_move(2, 2).unwrap();
+        assert_eq!(
+            game.board(),
+            &Board::new().set_piece((1, 1), Piece::Naught).unwrap()
+        );
+        assert_eq!(game.current_turn(), Piece::Cross);
+        assert_eq!(game.moves(), &[(Piece::Naught, 1, 1)]);
+        assert_eq!(branch.moves().len(), 3);
+        game.undo().unwrap();
+        assert!(game.undo().is_err());
+        assert_eq!(branch.board().move_count(), 3);
+    }
+
+    #[test]
+    fn only_an_untouched_game_is_fresh() {
+        let mut game = Game::new(None);
+        assert!(game.board().is_empty());
+        assert!(game.is_fresh());
+        game.apply_move(0, 0).unwrap();
+        assert!(!game.board().is_empty());
+        assert!(!game.is_fresh());
+    }
+}
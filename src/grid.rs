@@ -0,0 +1,68 @@
+use crate::board::{lines, Board};
+use crate::error::TicTacToeError;
+use crate::piece::{BoardPosition, Piece};
+use crate::position::Position;
+
+/// The operations the game needs from a board, so that other storage such
+/// as `SparseBoard` can stand in for the dense `Board`.
+pub trait PieceGrid: Sized {
+    fn width(&self) -> usize;
+
+    /// Grids are square unless they say otherwise.
+    fn height(&self) -> usize {
+        self.width()
+    }
+
+    fn get_position(&self, position: impl Into<Position>) -> Result<BoardPosition, TicTacToeError>;
+
+    fn set_piece(
+        &self,
+        position: impl Into<Position>,
+        piece: Piece,
+    ) -> Result<Self, TicTacToeError>;
+
+    fn empty_positions(&self) -> Box<dyn Iterator<Item = (usize, usize)> + '_>;
+
+    /// The piece with `win_length` in a row, checked line by line.
+    fn winner(&self, win_length: usize) -> Option<Piece> {
+        if win_length == 0 || win_length > self.width().max(self.height()) {
+            return None;
+        }
+        lines(self.width(), self.height(), win_length).find_map(|line| {
+            let piece = self.get_position(line[0]).ok()??;
+            line.iter()
+                .all(|&position| matches!(self.get_position(position), Ok(Some(p)) if p == piece))
+                .then_some(piece)
+        })
+    }
+}
+
+impl PieceGrid for Board {
+    fn width(&self) -> usize {
+        self.width()
+    }
+
+    fn height(&self) -> usize {
+        self.height()
+    }
+
+    fn get_position(&self, position: impl Into<Position>) -> Result<BoardPosition, TicTacToeError> {
+        self.get_position(position)
+    }
+
+    fn set_piece(
+        &self,
+        position: impl Into<Position>,
+        piece: Piece,
+    ) -> Result<Self, TicTacToeError> {
+        self.set_piece(position, piece)
+    }
+
+    fn empty_positions(&self) -> Box<dyn Iterator<Item = (usize, usize)> + '_> {
+        Box::new(self.empty_positions())
+    }
+
+    fn winner(&self, win_length: usize) -> Option<Piece> {
+        self.winner(win_length)
+    }
+}
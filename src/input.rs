@@ -0,0 +1,92 @@
+use crate::error::TicTacToeError;
+use crate::piece::Piece;
+
+pub fn parse_move(input: &str) -> Result<(usize, usize), TicTacToeError> {
+    let coords = input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .collect::<Vec<_>>();
+    match coords.as_slice() {
+        [x, y] => Ok((
+            x.parse::<usize>()
+                .map_err(|_| TicTacToeError::InvalidMoveInput)?,
+            y.parse::<usize>()
+                .map_err(|_| TicTacToeError::InvalidMoveInput)?,
+        )),
+        _ => Err(TicTacToeError::InvalidMoveInput),
+    }
+}
+
+/// Parses a cell number from 1 to `width * height`, counted row by row from
+/// the top-left, into (x, y) coordinates.
+pub fn parse_cell_number(
+    input: &str,
+    width: usize,
+    height: usize,
+) -> Result<(usize, usize), TicTacToeError> {
+    let number = input
+        .trim()
+        .parse::<usize>()
+        .map_err(|_| TicTacToeError::InvalidMoveInput)?;
+    if number == 0 || number > width * height {
+        return Err(TicTacToeError::InvalidMoveInput);
+    }
+    Ok(((number - 1) % width, (number - 1) / width))
+}
+
+/// Parses a piece from the symbol it is displayed as.
+pub(crate) fn parse_piece(symbol: &str) -> Option<Piece> {
+    match symbol {
+        "O" => Some(Piece::Naught),
+        "X" => Some(Piece::Cross),
+        "T" => Some(Piece::Triangle),
+        _ => None,
+    }
+}
+
+/// Parses a line of a move file: either "x y", or "P x y" as written by
+/// `Game::move_list`.
+pub(crate) fn parse_move_record(
+    input: &str,
+) -> Result<(Option<Piece>, usize, usize), TicTacToeError> {
+    let input = input.trim();
+    let piece = input.split_whitespace().next().and_then(parse_piece);
+    let coords = if piece.is_some() { &input[1..] } else { input };
+    let (x, y) = parse_move(coords)?;
+    Ok((piece, x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn too_few_tokens_are_invalid() {
+        assert!(matches!(
+            parse_move("5"),
+            Err(TicTacToeError::InvalidMoveInput)
+        ));
+        assert!(matches!(
+            parse_move(""),
+            Err(TicTacToeError::InvalidMoveInput)
+        ));
+    }
+
+    #[test]
+    fn parses_a_pair_of_coordinates() {
+        assert_eq!(parse_move("1 2").unwrap(), (1, 2));
+        assert_eq!(parse_move("1 2 \n").unwrap(), (1, 2));
+    }
+
+    #[test]
+    fn rejects_tokens_that_arent_coordinates() {
+        for input in &["a b", "-1 2", "99999999999999999999999 0"] {
+            assert!(
+                matches!(parse_move(input), Err(TicTacToeError::InvalidMoveInput)),
+                "{:?} should be invalid",
+                input
+            );
+        }
+    }
+}
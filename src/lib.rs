@@ -0,0 +1,36 @@
+mod ai;
+mod bitboard;
+mod board;
+mod error;
+mod game;
+mod grid;
+mod input;
+mod observer;
+mod piece;
+mod position;
+mod sparse;
+mod symbols;
+mod tournament;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use crate::ai::{AiPlayer, Difficulty, Strategy};
+pub use crate::bitboard::BitBoard;
+pub use crate::board::{Board, Threat, TicTacToeBoard};
+pub use crate::error::TicTacToeError;
+#[cfg(feature = "rand")]
+pub use crate::game::random_game;
+pub use crate::game::{
+    Command, Game, GameInProgress, GameOutcome, GameResult, GameState, GameStatus,
+    IllegalMovePolicy, Move, OutputFormat, Player,
+};
+pub use crate::grid::PieceGrid;
+pub use crate::input::{parse_cell_number, parse_move};
+pub use crate::observer::GameObserver;
+pub use crate::piece::{BoardPosition, Piece};
+pub use crate::position::Position;
+pub use crate::sparse::SparseBoard;
+pub use crate::symbols::PieceSymbols;
+pub use crate::tournament::{play_match, MatchResult};
+#[cfg(feature = "wasm")]
+pub use crate::wasm::WasmGame;
@@ -1,230 +1,305 @@
-use core::fmt::{self, Display};
-use std::io;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::process;
+use std::time::Duration;
 
-const BOARD_WIDTH: usize = 3;
+use tic_tac_toe::{AiPlayer, Difficulty, Game, GameStatus, OutputFormat, Piece};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Piece {
-    Naught,
-    Cross,
+#[derive(Debug, Default)]
+struct Options {
+    size: Option<(usize, usize)>,
+    win_length: Option<usize>,
+    vs_ai: bool,
+    ai_first: bool,
+    random_ai: bool,
+    easy_ai: bool,
+    random_start: bool,
+    three_players: bool,
+    one_based: bool,
+    cell_numbers: bool,
+    reverse: bool,
+    analysis: bool,
+    pie_rule: bool,
+    redraw: bool,
+    demo: bool,
+    games: Option<usize>,
+    demo_delay: Option<Duration>,
+    move_timeout: Option<Duration>,
+    format: Option<OutputFormat>,
+    load: Option<String>,
 }
 
-impl Display for Piece {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            &Piece::Cross => write!(f, "X"),
-            &Piece::Naught => write!(f, "O"),
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| format!("{} needs a value", flag));
+        match arg.as_str() {
+            "--size" => options.size = Some(parse_size(&value(&arg)?)?),
+            "--win-length" => options.win_length = Some(parse_number(&arg, &value(&arg)?)?),
+            "--move-timeout" => {
+                let seconds = parse_number(&arg, &value(&arg)?)?;
+                options.move_timeout = Some(Duration::from_secs(seconds as u64));
+            }
+            "--games" => options.games = Some(parse_number(&arg, &value(&arg)?)?),
+            "--demo-delay" => {
+                let value = value(&arg)?;
+                let millis = value.parse::<u64>().map_err(|_| {
+                    format!("{} must be a number of milliseconds, not {}", arg, value)
+                })?;
+                options.demo_delay = Some(Duration::from_millis(millis));
+            }
+            "--load" => options.load = Some(value(&arg)?),
+            "--format" => options.format = Some(parse_format(&value(&arg)?)?),
+            "--vs-ai" => options.vs_ai = true,
+            "--ai-first" => options.ai_first = true,
+            "--random-ai" => options.random_ai = true,
+            "--easy-ai" => options.easy_ai = true,
+            "--random-start" => options.random_start = true,
+            "--three-players" => options.three_players = true,
+            "--one-based" => options.one_based = true,
+            "--cell-numbers" => options.cell_numbers = true,
+            "--reverse" => options.reverse = true,
+            "--analysis" => options.analysis = true,
+            "--pie-rule" => options.pie_rule = true,
+            "--redraw" => options.redraw = true,
+            "--demo" => options.demo = true,
+            // A bare number is the board size, as before --size existed.
+            _ if arg.parse::<usize>().is_ok() => {
+                let size = parse_number("size", &arg)?;
+                options.size = Some((size, size));
+            }
+            _ => return Err(format!("unknown argument {}", arg)),
         }
     }
+    if options.ai_first && !options.vs_ai {
+        return Err("--ai-first only makes sense with --vs-ai".to_string());
+    }
+    if options.demo && options.vs_ai {
+        return Err("--demo has the computer play every side, so drop --vs-ai".to_string());
+    }
+    if !options.demo && (options.games.is_some() || options.demo_delay.is_some()) {
+        return Err("--games and --demo-delay only make sense with --demo".to_string());
+    }
+    if options.easy_ai && options.random_ai {
+        return Err("--easy-ai and --random-ai can't be used together".to_string());
+    }
+    if options.size.is_some() && options.load.is_some() {
+        return Err("--size can't be used with --load, which uses the saved board".to_string());
+    }
+    Ok(options)
 }
 
-type BoardPosition = Option<Piece>;
-
-#[derive(Debug, Clone)]
-struct Board {
-    positions: Vec<BoardPosition>,
+fn parse_number(flag: &str, value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|&number| number > 0)
+        .ok_or_else(|| format!("{} must be a positive number, not {}", flag, value))
 }
 
-impl Board {
-    fn new() -> Self {
-        Board {
-            positions: (0..BOARD_WIDTH * BOARD_WIDTH)
-                .into_iter()
-                .map(|_i| None)
-                .collect(),
+/// Reads a board size as either a single width for a square board, or
+/// WIDTHxHEIGHT.
+fn parse_size(value: &str) -> Result<(usize, usize), String> {
+    match value.split_once('x') {
+        Some((width, height)) => Ok((
+            parse_number("board width", width)?,
+            parse_number("board height", height)?,
+        )),
+        None => {
+            let size = parse_number("board size", value)?;
+            Ok((size, size))
         }
     }
+}
 
-    fn get_position(&self, x: usize, y: usize) -> Result<BoardPosition, TicTacToeError> {
-        if x >= BOARD_WIDTH || y >= BOARD_WIDTH {
-            return Err(TicTacToeError::OutOfBounds);
+/// Asks `question` on stdin until the answer parses, taking `default` for an
+/// empty answer or the end of input.
+fn prompt<T>(question: &str, default: T, parse: impl Fn(&str) -> Result<T, String>) -> T {
+    loop {
+        print!("{} ", question);
+        io::stdout().flush().ok();
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).unwrap_or(0) == 0 || answer.trim().is_empty() {
+            return default;
         }
-        Ok(self.positions[y * BOARD_WIDTH + x])
-    }
-
-    fn set_piece(&self, x: usize, y: usize, piece: Piece) -> Result<Self, TicTacToeError> {
-        if let Some(_piece) = self.get_position(x, y)? {
-            return Err(TicTacToeError::PieceInPosition(x, y));
+        match parse(answer.trim()) {
+            Ok(value) => return value,
+            Err(e) => println!("{}", e),
         }
-        Ok(Board {
-            positions: self
-                .clone()
-                .positions
-                .into_iter()
-                .enumerate()
-                .map(|(i, position)| {
-                    if i == y * BOARD_WIDTH + x {
-                        Some(piece)
-                    } else {
-                        position
-                    }
-                })
-                .collect(),
-            ..*self
-        })
     }
 }
 
-impl Display for Board {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for i in 0..BOARD_WIDTH {
-            for j in 0..BOARD_WIDTH {
-                match self.positions[i * BOARD_WIDTH + j] {
-                    None => write!(f, ".")?,
-                    Some(piece) => write!(f, "{}", piece)?,
-                }
-                write!(f, " ")?;
+// Asks for the board size, and the win length on boards bigger than 3x3,
+// unless they were given as arguments. Piped input is left for the moves.
+fn prompt_for_size(options: &mut Options) {
+    if options.size.is_some() || options.load.is_some() || !io::stdin().is_terminal() {
+        return;
+    }
+    let (width, height) = prompt("Board size (default 3):", (3, 3), parse_size);
+    options.size = Some((width, height));
+    let longest = width.max(height);
+    if longest > 3 && options.win_length.is_none() {
+        let default = width.min(height);
+        let question = format!("Win length (default {}):", default);
+        options.win_length = Some(prompt(&question, default, |value| {
+            let win_length = parse_number("win length", value)?;
+            if win_length > longest {
+                return Err(format!(
+                    "win length can't be longer than the board's longest side ({})",
+                    longest
+                ));
             }
-            write!(f, "\n")?;
-        }
-        Ok(())
+            Ok(win_length)
+        }));
     }
 }
 
-#[derive(Debug)]
-enum TicTacToeError {
-    OutOfBounds,
-    PieceInPosition(usize, usize),
-    InvalidMoveInput,
+fn parse_format(value: &str) -> Result<OutputFormat, String> {
+    match value {
+        "human" => Ok(OutputFormat::Human),
+        #[cfg(feature = "serde")]
+        "json" => Ok(OutputFormat::Json),
+        #[cfg(not(feature = "serde"))]
+        "json" => Err("--format json needs the serde feature".to_string()),
+        _ => Err(format!("--format must be human or json, not {}", value)),
+    }
 }
 
-#[derive(Debug)]
-struct GameState {
-    board: Board,
-    turn: Piece,
+#[cfg(feature = "rand")]
+fn ai_difficulty(random_ai: bool) -> Difficulty {
+    if random_ai {
+        Difficulty::Random
+    } else {
+        Difficulty::Perfect
+    }
 }
 
-#[derive(Debug)]
-struct Game {
-    state: GameState,
-    winning_positions: Vec<Vec<(usize, usize)>>,
+#[cfg(not(feature = "rand"))]
+fn ai_difficulty(_random_ai: bool) -> Difficulty {
+    Difficulty::Perfect
 }
 
-impl Game {
-    fn new() -> Self {
-        Game {
-            state: GameState {
-                board: Board::new(),
-                turn: Piece::Naught,
-            },
-            winning_positions: (0..BOARD_WIDTH)
-                .into_iter()
-                .flat_map(|i| {
-                    {
-                        vec![
-                            (0..BOARD_WIDTH).into_iter().map(move |j| (j, i)).collect(),
-                            (0..BOARD_WIDTH).into_iter().map(move |j| (i, j)).collect(),
-                        ]
-                    }
-                })
-                .chain(vec![
-                    (0..BOARD_WIDTH).into_iter().map(|i| (i, i)).collect(),
-                    (0..BOARD_WIDTH)
-                        .into_iter()
-                        .map(|i| (BOARD_WIDTH - 1 - i, i))
-                        .collect(),
-                ])
-                .collect(),
-        }
+#[cfg(feature = "rand")]
+fn configure_random_start(game: Game, random_start: bool) -> Game {
+    if random_start {
+        game.with_random_start(None)
+    } else {
+        game
     }
+}
 
-    fn reset_state(&self) -> GameState {
-        GameState {
-            board: Board::new(),
-            turn: self.change_turn(),
-        }
-    }
+#[cfg(not(feature = "rand"))]
+fn configure_random_start(game: Game, _random_start: bool) -> Game {
+    game
+}
 
-    fn display_board(&self) {
-        println!("{}", self.state.board);
-    }
+#[cfg(feature = "serde")]
+fn load_game(path: &str) -> Game {
+    Game::load(Path::new(path)).unwrap_or_else(|e| {
+        eprintln!("Could not load {}: {}", path, e);
+        process::exit(1);
+    })
+}
 
-    fn change_turn(&self) -> Piece {
-        match self.state.turn {
-            Piece::Naught => Piece::Cross,
-            Piece::Cross => Piece::Naught,
-        }
-    }
+#[cfg(not(feature = "serde"))]
+fn load_game(_path: &str) -> Game {
+    eprintln!("Loading a saved game needs the serde feature");
+    process::exit(1);
+}
 
-    fn get_move(&self) -> Result<(usize, usize), TicTacToeError> {
-        let mut position_str_raw = String::new();
-        io::stdin()
-            .read_line(&mut position_str_raw)
-            .map_err(|_| TicTacToeError::InvalidMoveInput)?;
-        let coords = position_str_raw.trim().split(" ").collect::<Vec<_>>();
-        Ok((
-            match coords[0].parse::<usize>() {
-                Ok(i) => i,
-                Err(..) => return Err(TicTacToeError::InvalidMoveInput),
-            },
-            match coords[1].parse::<usize>() {
-                Ok(i) => i,
-                Err(..) => return Err(TicTacToeError::InvalidMoveInput),
-            },
-        ))
+fn main() {
+    // The library logs moves and results through `log`, which prints nothing
+    // until a logger is installed. Build with the logging feature and set
+    // RUST_LOG=debug (or trace) to see them.
+    #[cfg(feature = "logging")]
+    env_logger::init();
+    let mut options = parse_args(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    // JSON output is read by other programs, so keep stdout for the boards.
+    let json = matches!(options.format, Some(format) if format != OutputFormat::Human);
+    if !json {
+        prompt_for_size(&mut options);
     }
-
-    fn check_slice_for_winner(&self, slice: &Vec<(usize, usize)>) -> Option<Piece> {
-        let positions: Vec<BoardPosition> = slice
-            .iter()
-            .map(|(x, y)| {
-                self.state.board.get_position(*x, *y).expect(
-                    format!(
-                        "winning positions have invalid coords in them: {}, {}",
-                        x, y
-                    )
-                    .as_str(),
+    let game = match &options.load {
+        Some(path) => {
+            let game = load_game(path);
+            let note = if game.is_fresh() {
+                format!("{} has no moves yet, so starting from the beginning.", path)
+            } else if game.status() != GameStatus::InProgress {
+                format!("{} holds a finished game.", path)
+            } else {
+                format!(
+                    "Resuming {} at move {}.",
+                    path,
+                    game.board().move_count() + 1
                 )
-            })
-            .collect();
-        let position = positions
-            .get(0)
-            .expect("winning positions have slices of length 0");
-        if positions.iter().all(|p| p.is_some() && p == position) {
-            return *position;
-        }
-        None
-    }
-
-    fn check_winner(&self) -> Option<Piece> {
-        for slice in self.winning_positions.iter() {
-            if let Some(winner) = self.check_slice_for_winner(slice) {
-                return Some(winner);
+            };
+            if json {
+                eprintln!("{}", note);
+            } else {
+                println!("{}", note);
             }
+            game
         }
-        return None;
+        None => match options.size {
+            Some((width, height)) if width != height => Game::new_rectangular(width, height),
+            size => Game::new(size.map(|(width, _)| width)),
+        },
+    };
+    let mut game = game
+        .with_one_based(options.one_based)
+        .with_cell_numbers(options.cell_numbers)
+        .with_reverse(options.reverse)
+        .with_analysis(options.analysis)
+        .with_pie_rule(options.pie_rule)
+        .with_redraw(options.redraw);
+    if let Some(win_length) = options.win_length {
+        let longest = game.board().width().max(game.board().height());
+        if win_length > longest {
+            eprintln!(
+                "--win-length {} is longer than the board's longest side ({})",
+                win_length, longest
+            );
+            process::exit(1);
+        }
+        game = game.with_win_length(win_length);
     }
-
-    fn run(mut self) {
-        println!("Starting the game!");
-        loop {
-            loop {
-                self.display_board();
-                println!("Pick a position:");
-                loop {
-                    match {
-                        self.get_move()
-                            .and_then(|(x, y)| self.state.board.set_piece(x, y, self.state.turn))
-                    } {
-                        Ok(board) => {
-                            self.state.board = board;
-                            break;
-                        }
-                        Err(_) => println!("Invalid move. Try again:"),
-                    };
-                }
-                if let Some(winner) = self.check_winner() {
-                    println!("{} won!", winner);
-                    break;
-                }
-                self.state.turn = self.change_turn();
-            }
-            println!("Starting a new game!");
-            self.state = self.reset_state();
+    if options.three_players {
+        game = game.with_players(vec![Piece::Naught, Piece::Cross, Piece::Triangle]);
+    }
+    game = configure_random_start(game, options.random_start);
+    let difficulty = if options.easy_ai {
+        Difficulty::Easy
+    } else {
+        ai_difficulty(options.random_ai)
+    };
+    if options.demo {
+        for piece in game.players().to_vec() {
+            game = game.with_ai(AiPlayer::new(piece, difficulty));
+        }
+        game = game.with_ai_games(options.games.unwrap_or(1));
+        if let Some(delay) = options.demo_delay {
+            game = game.with_ai_delay(delay);
         }
+    } else if options.vs_ai {
+        // Naught moves first unless the start is random, so the computer takes
+        // it to go first.
+        let piece = if options.ai_first {
+            Piece::Naught
+        } else {
+            Piece::Cross
+        };
+        game = game.with_ai(AiPlayer::new(piece, difficulty));
     }
-}
-
-fn main() {
-    Game::new().run();
+    if let Some(format) = options.format {
+        game = game.with_output_format(format);
+    }
+    if let Some(timeout) = options.move_timeout {
+        game = game.with_move_timeout(timeout);
+    }
+    game.run();
 }
@@ -0,0 +1,17 @@
+use core::fmt;
+
+use crate::piece::Piece;
+
+/// Receives notifications as a `Game` is played. Every method does nothing
+/// by default, so implementors only override the events they care about.
+pub trait GameObserver {
+    fn on_move(&mut self, _piece: Piece, _x: usize, _y: usize) {}
+    fn on_win(&mut self, _winner: Piece) {}
+    fn on_draw(&mut self) {}
+}
+
+impl fmt::Debug for dyn GameObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GameObserver")
+    }
+}
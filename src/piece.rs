@@ -0,0 +1,51 @@
+use core::fmt::{self, Display};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Piece {
+    Naught,
+    Cross,
+    Triangle,
+}
+
+impl Piece {
+    /// The piece across the board in a two-player game, so Naught and Cross
+    /// face each other. A Triangle only plays in three-player games, where
+    /// nobody has a single opponent, so it has none.
+    pub fn opponent(self) -> Option<Piece> {
+        match self {
+            Piece::Naught => Some(Piece::Cross),
+            Piece::Cross => Some(Piece::Naught),
+            Piece::Triangle => None,
+        }
+    }
+}
+
+impl Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Piece::Cross => write!(f, "X"),
+            Piece::Naught => write!(f, "O"),
+            Piece::Triangle => write!(f, "T"),
+        }
+    }
+}
+
+pub type BoardPosition = Option<Piece>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opponent_is_an_involution() {
+        for &piece in &[Piece::Naught, Piece::Cross] {
+            assert_ne!(piece.opponent(), Some(piece));
+            assert_eq!(piece.opponent().and_then(Piece::opponent), Some(piece));
+        }
+        assert_eq!(Piece::Triangle.opponent(), None);
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use crate::error::TicTacToeError;
+use crate::grid::PieceGrid;
+use crate::piece::{BoardPosition, Piece};
+use crate::position::Position;
+
+/// A board that only stores its occupied cells, for large boards that stay
+/// mostly empty. Copying it costs the number of pieces rather than the
+/// number of cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseBoard {
+    width: usize,
+    pieces: HashMap<Position, Piece>,
+}
+
+impl SparseBoard {
+    pub fn with_size(width: usize) -> Self {
+        SparseBoard {
+            width,
+            pieces: HashMap::new(),
+        }
+    }
+
+    pub fn set_piece_mut(
+        &mut self,
+        position: impl Into<Position>,
+        piece: Piece,
+    ) -> Result<(), TicTacToeError> {
+        let position = position.into();
+        if self.get_position(position)?.is_some() {
+            return Err(TicTacToeError::PieceInPosition(position.x, position.y));
+        }
+        self.pieces.insert(position, piece);
+        Ok(())
+    }
+
+    pub fn occupied_positions(&self) -> impl Iterator<Item = (usize, usize, Piece)> + '_ {
+        self.pieces
+            .iter()
+            .map(|(&Position { x, y }, &piece)| (x, y, piece))
+    }
+}
+
+impl PieceGrid for SparseBoard {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn get_position(&self, position: impl Into<Position>) -> Result<BoardPosition, TicTacToeError> {
+        let Position { x, y } = position.into();
+        if x >= self.width || y >= self.width {
+            return Err(TicTacToeError::OutOfBounds(x, y));
+        }
+        Ok(self.pieces.get(&Position::new(x, y)).copied())
+    }
+
+    fn set_piece(
+        &self,
+        position: impl Into<Position>,
+        piece: Piece,
+    ) -> Result<Self, TicTacToeError> {
+        let mut board = self.clone();
+        board.set_piece_mut(position, piece)?;
+        Ok(board)
+    }
+
+    fn empty_positions(&self) -> Box<dyn Iterator<Item = (usize, usize)> + '_> {
+        Box::new(
+            (0..self.width)
+                .flat_map(move |y| (0..self.width).map(move |x| (x, y)))
+                .filter(move |&(x, y)| !self.pieces.contains_key(&Position::new(x, y))),
+        )
+    }
+
+    // Every winning line starts on an occupied cell, so only runs starting
+    // from a piece need checking instead of every line on the board.
+    fn winner(&self, win_length: usize) -> Option<Piece> {
+        if win_length == 0 || win_length > self.width {
+            return None;
+        }
+        let directions: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (-1, 1)];
+        self.occupied_positions().find_map(|(x, y, piece)| {
+            directions
+                .iter()
+                .any(|&(dx, dy)| {
+                    (1..win_length as isize).all(|k| {
+                        // Stepping left off the board goes negative, and any
+                        // other cell off the board just isn't in the map.
+                        let cx = x as isize + dx * k;
+                        let cy = y as isize + dy * k;
+                        cx >= 0
+                            && self.pieces.get(&Position::new(cx as usize, cy as usize))
+                                == Some(&piece)
+                    })
+                })
+                .then_some(piece)
+        })
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::piece::Piece;
+
+/// The glyphs each piece and empty cells are shown as, which default to the
+/// piece's own `Display` and `.`. Symbols may be any width, including emoji,
+/// and renderers pad every cell to the widest one so the grid stays aligned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieceSymbols {
+    symbols: HashMap<Piece, String>,
+    empty: String,
+}
+
+impl PieceSymbols {
+    pub fn new() -> Self {
+        PieceSymbols {
+            symbols: [Piece::Naught, Piece::Cross, Piece::Triangle]
+                .iter()
+                .map(|&piece| (piece, piece.to_string()))
+                .collect(),
+            empty: ".".to_string(),
+        }
+    }
+
+    pub fn with_symbol(mut self, piece: Piece, symbol: impl Into<String>) -> Self {
+        self.symbols.insert(piece, symbol.into());
+        self
+    }
+
+    pub fn with_empty(mut self, glyph: impl Into<String>) -> Self {
+        self.empty = glyph.into();
+        self
+    }
+
+    pub fn symbol(&self, piece: Piece) -> &str {
+        &self.symbols[&piece]
+    }
+
+    pub fn empty(&self) -> &str {
+        &self.empty
+    }
+
+    /// The display width of the widest symbol, and at least one column.
+    pub fn width(&self) -> usize {
+        self.symbols
+            .values()
+            .chain(Some(&self.empty))
+            .map(|symbol| symbol.width())
+            .max()
+            .unwrap_or_default()
+            .max(1)
+    }
+
+    /// Right-aligns `glyph` to `width` display columns.
+    pub(crate) fn pad(glyph: &str, width: usize) -> String {
+        format!(
+            "{}{}",
+            " ".repeat(width.saturating_sub(glyph.width())),
+            glyph
+        )
+    }
+}
+
+impl Default for PieceSymbols {
+    fn default() -> Self {
+        PieceSymbols::new()
+    }
+}
@@ -0,0 +1,137 @@
+use crate::ai::Strategy;
+use crate::game::{Game, GameStatus};
+use crate::piece::Piece;
+
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Tally of a match, from the first player's point of view, along with how
+/// many games each piece won whichever player held it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchResult {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub wins_cross: u32,
+    pub wins_naught: u32,
+}
+
+impl MatchResult {
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    /// Renders the share of X wins, O wins and draws as ASCII bars scaled to
+    /// `HISTOGRAM_WIDTH` characters. A match with no games shows empty bars.
+    pub fn histogram(&self) -> String {
+        let games = self.games();
+        [
+            (format!("{} wins", Piece::Cross), self.wins_cross),
+            (format!("{} wins", Piece::Naught), self.wins_naught),
+            ("Draws".to_string(), self.draws),
+        ]
+        .iter()
+        .map(|(label, count)| {
+            let count = *count;
+            let share = if games == 0 {
+                0.0
+            } else {
+                f64::from(count) / f64::from(games)
+            };
+            let filled = (share * HISTOGRAM_WIDTH as f64).round() as usize;
+            format!(
+                "{:<6} |{}{}| {:5.1}% ({})\n",
+                label,
+                "#".repeat(filled),
+                " ".repeat(HISTOGRAM_WIDTH - filled),
+                share * 100.0,
+                count
+            )
+        })
+        .collect()
+    }
+}
+
+/// Plays `games` default-sized games between `p1` and `p2`, with `p1` moving
+/// first in even-numbered games and `p2` in odd ones. A strategy that picks
+/// an illegal move loses that game.
+pub fn play_match(p1: &dyn Strategy, p2: &dyn Strategy, games: usize) -> MatchResult {
+    let mut result = MatchResult::default();
+    for index in 0..games {
+        let mut game = Game::new(None);
+        let first = game.current_turn();
+        let p1_starts = index % 2 == 0;
+        loop {
+            let turn = game.current_turn();
+            let p1_to_move = (turn == first) == p1_starts;
+            let strategy = if p1_to_move { p1 } else { p2 };
+            let (x, y) = strategy.choose(game.board(), turn);
+            let winner = if game.apply_move(x, y).is_err() {
+                if p1_to_move {
+                    result.losses += 1;
+                } else {
+                    result.wins += 1;
+                }
+                turn.opponent()
+            } else {
+                match game.status() {
+                    GameStatus::Win(winner) => {
+                        if p1_to_move {
+                            result.wins += 1;
+                        } else {
+                            result.losses += 1;
+                        }
+                        Some(winner)
+                    }
+                    GameStatus::Draw => {
+                        result.draws += 1;
+                        None
+                    }
+                    GameStatus::InProgress => continue,
+                }
+            };
+            match winner {
+                Some(Piece::Cross) => result.wins_cross += 1,
+                Some(Piece::Naught) => result.wins_naught += 1,
+                _ => {}
+            }
+            break;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{AiPlayer, Difficulty};
+
+    // Always plays the first empty cell, so the first mover wins down the
+    // left-hand column.
+    struct FirstEmpty;
+
+    impl Strategy for FirstEmpty {
+        fn choose(&self, board: &crate::board::Board, _turn: Piece) -> (usize, usize) {
+            board.empty_positions().next().unwrap()
+        }
+    }
+
+    #[test]
+    fn histogram_bars_are_labelled_by_piece() {
+        let result = play_match(&FirstEmpty, &FirstEmpty, 4);
+        assert_eq!(result.wins_cross + result.wins_naught + result.draws, 4);
+        let histogram = result.histogram();
+        let labels = histogram
+            .lines()
+            .map(|line| line.split('|').next().unwrap().trim())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["X wins", "O wins", "Draws"]);
+    }
+
+    #[test]
+    fn perfect_play_only_draws() {
+        let perfect = AiPlayer::new(Piece::Naught, Difficulty::Perfect);
+        let result = play_match(&perfect, &perfect, 2);
+        assert_eq!(result.draws, 2);
+        assert_eq!(result.wins_cross + result.wins_naught, 0);
+    }
+}
@@ -0,0 +1,51 @@
+use wasm_bindgen::prelude::*;
+
+use crate::game::{Game, GameStatus};
+
+/// A `Game` driven from JavaScript through `apply_move`, since the
+/// stdin-based `run` loop isn't available in the browser.
+#[wasm_bindgen(js_name = Game)]
+pub struct WasmGame {
+    game: Game,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl WasmGame {
+    #[wasm_bindgen(constructor)]
+    pub fn new(size: Option<usize>) -> WasmGame {
+        WasmGame {
+            game: Game::new(size),
+        }
+    }
+
+    #[wasm_bindgen(js_name = applyMove)]
+    pub fn apply_move(&mut self, x: usize, y: usize) -> Result<(), JsValue> {
+        self.game
+            .apply_move(x, y)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = boardString)]
+    pub fn board_string(&self) -> String {
+        self.game.board().render_plain()
+    }
+
+    #[wasm_bindgen(js_name = currentTurn)]
+    pub fn current_turn(&self) -> String {
+        self.game.current_turn().to_string()
+    }
+
+    /// Returns "in-progress", "draw", or the winning piece's symbol.
+    pub fn status(&self) -> String {
+        match self.game.status() {
+            GameStatus::InProgress => "in-progress".to_string(),
+            GameStatus::Win(winner) => winner.to_string(),
+            GameStatus::Draw => "draw".to_string(),
+        }
+    }
+
+    #[wasm_bindgen(js_name = startNewGame)]
+    pub fn start_new_game(&mut self) {
+        self.game.start_new_game();
+    }
+}